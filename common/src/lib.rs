@@ -11,6 +11,19 @@ pub fn time_execution<T>(name: &str, f: impl Fn() -> T) -> T {
     result
 }
 
+/// Deterministic pseudo-random numbers below the given bound (linear congruential generator), for
+/// tests.
+#[cfg(feature = "test-util")]
+pub fn random_numbers(seed: u64) -> impl FnMut(u64) -> u64 {
+    let mut state = seed;
+    move |bound| {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (state >> 33) % bound
+    }
+}
+
 #[cfg(test)]
 mod tests {
     #[test]
@@ -0,0 +1,161 @@
+use crate::ValidationResult;
+
+/// Index of a pair of delimiters in its [`DelimiterSet`].
+pub type DelimiterType = usize;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Bracket {
+    Open(DelimiterType),
+    Close(DelimiterType),
+}
+
+/// Delimiter, with its column in the line (starting at 0, in characters).
+pub type Token = (usize, Bracket);
+
+/// Delimiters recognized when validating lines.
+///
+/// Symmetric delimiters (such as quotes) open and close with the same character, and their
+/// content is not interpreted: only the closing delimiter and the escape character matter
+/// there. The escape character makes the next character ignored, inside or outside of symmetric
+/// delimiters.
+#[derive(Debug, Clone)]
+pub struct DelimiterSet {
+    pairs: Vec<(char, char)>,
+    escape: Option<char>,
+    ignored: Vec<char>,
+    /// Whether characters which are not delimiters are ignored, rather than rejected.
+    ignore_others: bool,
+}
+
+impl DelimiterSet {
+    pub fn new(pairs: &[(char, char)]) -> Result<DelimiterSet, String> {
+        let mut set = DelimiterSet {
+            pairs: Vec::new(),
+            escape: None,
+            ignored: Vec::new(),
+            ignore_others: false,
+        };
+        for (open, close) in pairs {
+            set.check_unused(*open)?;
+            if close != open {
+                set.check_unused(*close)?;
+            }
+            set.pairs.push((*open, *close));
+        }
+        Ok(set)
+    }
+
+    /// The four pairs of brackets from the puzzle: `()`, `[]`, `{}` and `<>`.
+    pub fn puzzle() -> DelimiterSet {
+        DelimiterSet::new(&[('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')]).unwrap()
+    }
+
+    pub fn with_escape(mut self, escape: char) -> Result<DelimiterSet, String> {
+        self.check_unused(escape)?;
+        self.escape = Some(escape);
+        Ok(self)
+    }
+
+    pub fn with_ignored(mut self, ignored: &[char]) -> Result<DelimiterSet, String> {
+        for c in ignored {
+            self.check_unused(*c)?;
+            self.ignored.push(*c);
+        }
+        Ok(self)
+    }
+
+    /// Ignores all the characters which are not delimiters or escape characters.
+    pub fn ignoring_others(mut self) -> DelimiterSet {
+        self.ignore_others = true;
+        self
+    }
+
+    pub fn open_char(&self, delimiter: DelimiterType) -> char {
+        self.pairs[delimiter].0
+    }
+
+    pub fn close_char(&self, delimiter: DelimiterType) -> char {
+        self.pairs[delimiter].1
+    }
+
+    pub fn is_symmetric(&self, delimiter: DelimiterType) -> bool {
+        self.pairs[delimiter].0 == self.pairs[delimiter].1
+    }
+
+    fn check_unused(&self, c: char) -> Result<(), String> {
+        let used = self
+            .pairs
+            .iter()
+            .any(|(open, close)| *open == c || *close == c)
+            || self.escape == Some(c)
+            || self.ignored.contains(&c);
+        if used {
+            Err(format!("Character {:?} is used twice", c))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Delimiters of a line, with their column, or the first illegal character of the line (as a
+    /// [`ValidationResult::IllegalCharacter`]).
+    pub fn tokenize(&self, line: &str) -> Result<Vec<Token>, ValidationResult> {
+        let mut tokenizer = self.tokenizer();
+        let mut brackets = Vec::new();
+        for (column, c) in line.chars().enumerate() {
+            match tokenizer.push(c) {
+                Ok(Some(bracket)) => brackets.push((column, bracket)),
+                Ok(None) => {}
+                Err(_) => {
+                    return Err(ValidationResult::IllegalCharacter { character: Some(c), column })
+                }
+            }
+        }
+        Ok(brackets)
+    }
+
+    /// Tokenizer fed one character at a time, for lines which are not available at once.
+    pub fn tokenizer(&self) -> Tokenizer<'_> {
+        Tokenizer { delimiters: self, inside_symmetric: None, escaped: false }
+    }
+}
+
+pub struct Tokenizer<'a> {
+    delimiters: &'a DelimiterSet,
+    inside_symmetric: Option<DelimiterType>,
+    /// Whether the previous character was the escape character.
+    escaped: bool,
+}
+
+impl Tokenizer<'_> {
+    /// The delimiter for the next character of the line, if it is one.
+    pub fn push(&mut self, c: char) -> Result<Option<Bracket>, String> {
+        let delimiters = self.delimiters;
+        if self.escaped {
+            self.escaped = false;
+            return Ok(None);
+        }
+        if Some(c) == delimiters.escape {
+            self.escaped = true;
+            return Ok(None);
+        }
+        if let Some(delimiter) = self.inside_symmetric {
+            if c == delimiters.close_char(delimiter) {
+                self.inside_symmetric = None;
+                return Ok(Some(Bracket::Close(delimiter)));
+            }
+            return Ok(None);
+        }
+        if let Some(delimiter) = delimiters.pairs.iter().position(|(open, _)| *open == c) {
+            if delimiters.is_symmetric(delimiter) {
+                self.inside_symmetric = Some(delimiter);
+            }
+            Ok(Some(Bracket::Open(delimiter)))
+        } else if let Some(delimiter) = delimiters.pairs.iter().position(|(_, close)| *close == c) {
+            Ok(Some(Bracket::Close(delimiter)))
+        } else if !delimiters.ignore_others && !delimiters.ignored.contains(&c) {
+            Err(format!("Illegal character {}", c))
+        } else {
+            Ok(None)
+        }
+    }
+}
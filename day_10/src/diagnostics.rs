@@ -0,0 +1,81 @@
+use crate::delimiters::DelimiterSet;
+use crate::ValidationResult;
+
+/// Human-readable explanation of an invalid line: the line itself, a line of markers (`-` under
+/// the relevant openers, `^` under the error), then a message. Columns in the message start at 1.
+/// `None` if the line is valid.
+pub fn render_diagnostic(
+    line: &str,
+    result: &ValidationResult,
+    delimiters: &DelimiterSet,
+) -> Option<String> {
+    let end = line.chars().count();
+    let message = describe(result, end, delimiters)?;
+    let (openers, error_column) = match result {
+        ValidationResult::Valid => return None,
+        ValidationResult::Corrupted { expected, column, .. } => (
+            expected.iter().map(|opener| opener.column).collect(),
+            *column,
+        ),
+        ValidationResult::IllegalCharacter { column, .. } => (Vec::new(), *column),
+        ValidationResult::Incomplete { pending } => (
+            pending
+                .iter()
+                .map(|opener| opener.column)
+                .collect::<Vec<_>>(),
+            end,
+        ),
+    };
+
+    let mut markers = vec![' '; error_column + 1];
+    for column in openers {
+        markers[column] = '-';
+    }
+    markers[error_column] = '^';
+    let markers = markers.into_iter().collect::<String>();
+    Some(format!("{}\n{}\n{}", line, markers, message))
+}
+
+/// One-line explanation of an invalid line, for a line of `end` characters. Columns start at 1.
+/// `None` if the line is valid.
+pub fn describe(
+    result: &ValidationResult,
+    end: usize,
+    delimiters: &DelimiterSet,
+) -> Option<String> {
+    match result {
+        ValidationResult::Valid => None,
+        ValidationResult::Corrupted { expected: None, found, column } => Some(format!(
+            "column {}: found '{}' but no delimiter is open",
+            column + 1,
+            delimiters.close_char(*found)
+        )),
+        ValidationResult::Corrupted { expected: Some(opener), found, column } => Some(format!(
+            "column {}: expected '{}' to close '{}' from column {}, found '{}'",
+            column + 1,
+            delimiters.close_char(opener.delimiter),
+            delimiters.open_char(opener.delimiter),
+            opener.column + 1,
+            delimiters.close_char(*found)
+        )),
+        ValidationResult::IllegalCharacter { character: Some(character), column } => Some(format!(
+            "column {}: illegal character {:?}",
+            column + 1,
+            character
+        )),
+        ValidationResult::IllegalCharacter { character: None, column } => {
+            Some(format!("column {}: invalid UTF-8", column + 1))
+        }
+        ValidationResult::Incomplete { pending } => {
+            let missing = pending
+                .iter()
+                .map(|opener| delimiters.close_char(opener.delimiter))
+                .collect::<String>();
+            Some(format!(
+                "column {}: expected '{}' to close the pending delimiters",
+                end + 1,
+                missing
+            ))
+        }
+    }
+}
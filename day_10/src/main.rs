@@ -1,125 +1,270 @@
-use crate::Bracket::{Close, Open};
-use crate::BracketType::{Angle, Curly, Round, Square};
-use crate::ValidationResult::{Corrupted, Incomplete, Valid};
+use crate::delimiters::Bracket::{Close, Open};
+use crate::delimiters::{DelimiterSet, DelimiterType, Token};
+use crate::diagnostics::{describe, render_diagnostic};
+use crate::repair::{apply, repair, EditCosts};
+use crate::stream::{Issue, Recovery, StreamValidator};
+use crate::ValidationResult::{Corrupted, IllegalCharacter, Incomplete, Valid};
 use common::itertools::Itertools;
-use std::collections::{BinaryHeap, VecDeque};
-use std::fmt::format;
+use std::io::Read;
+
+mod delimiters;
+mod diagnostics;
+mod repair;
+mod stream;
 
 static INPUT: &str = include_str!("input");
 
 fn main() -> Result<(), String> {
-    let lines = parse_input(INPUT)?;
+    let options = parse_args(std::env::args().skip(1))?;
+    let delimiters = DelimiterSet::puzzle();
+    let lines = parse_input(INPUT, &delimiters)?;
 
-    let part_1_result = part_1(&lines);
+    let part_1_result = part_1(&lines, &delimiters);
     println!("Part 1 result: {}", part_1_result);
 
-    let part_2_result = part_2(&lines)?;
+    let part_2_result = part_2(&lines, &delimiters)?;
     println!("Part 2 result: {}", part_2_result);
 
+    if let Some(file) = &options.check_file {
+        let content = std::fs::read_to_string(file)
+            .map_err(|err| format!("Cannot read {}: {}", file, err))?;
+        let delimiters = &options.delimiters;
+        for (index, line) in content.lines().enumerate() {
+            let result = validate_line(line, delimiters);
+            if let Some(diagnostic) = render_diagnostic(line, &result, delimiters) {
+                println!("Line {}:\n{}", index + 1, diagnostic);
+            }
+        }
+    }
+
+    if let Some(file) = &options.repair_file {
+        let content = std::fs::read_to_string(file)
+            .map_err(|err| format!("Cannot read {}: {}", file, err))?;
+        let delimiters = &options.delimiters;
+        for (index, line) in content.lines().enumerate() {
+            // Only delimiters are repaired: lines with illegal characters are reported as is.
+            let tokens = match delimiters.tokenize(line) {
+                Ok(tokens) => tokens,
+                Err(illegal) => {
+                    let end = line.chars().count();
+                    let message = describe(&illegal, end, delimiters).unwrap_or_default();
+                    println!("Line {}: {}", index + 1, message);
+                    continue;
+                }
+            };
+            let repair = repair(&tokens, line.chars().count(), &options.edit_costs);
+            if !repair.edits.is_empty() {
+                let repaired = apply(line, &repair.edits, delimiters);
+                println!("Line {} (cost {}): {}", index + 1, repair.cost, repaired);
+            }
+        }
+    }
+
+    if let Some(file) = &options.stream_file {
+        stream_check(file, &options.delimiters, options.recovery)?;
+    }
+
     Ok(())
 }
 
-fn part_1<U>(lines: &[U]) -> usize
+/// Validates a file (or the standard input for `-`) without loading it into memory, printing
+/// issues as they are found.
+fn stream_check(file: &str, delimiters: &DelimiterSet, recovery: Recovery) -> Result<(), String> {
+    let mut input: Box<dyn Read> = if file == "-" {
+        Box::new(std::io::stdin())
+    } else {
+        Box::new(std::fs::File::open(file).map_err(|err| format!("Cannot read {}: {}", file, err))?)
+    };
+    let print_issues = |issues: Vec<Issue>| {
+        for issue in issues {
+            let message = describe(&issue.result, issue.end, delimiters).unwrap_or_default();
+            println!("Line {}: {}", issue.line + 1, message);
+        }
+    };
+    let mut validator = StreamValidator::new(delimiters, recovery);
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = input
+            .read(&mut buffer)
+            .map_err(|err| format!("Cannot read {}: {}", file, err))?;
+        if read == 0 {
+            break;
+        }
+        print_issues(validator.feed(&buffer[..read]));
+    }
+    print_issues(validator.finish());
+    Ok(())
+}
+
+/// Command line arguments: `[--check FILE] [--repair FILE] [--edit-costs INSERT,DELETE,REPLACE]
+/// [--stream FILE] [--recovery skip-line|ignore|pop|stop] [--pairs PAIRS] [--symmetric CHARS]
+/// [--escape CHAR] [--ignore CHARS] [--ignore-others]`, to validate or repair each line of a file
+/// (`--stream` also accepts `-` for the standard input). `PAIRS` lists the opening then closing
+/// character of each pair, e.g. `()[]` (the puzzle brackets by default).
+struct Options {
+    check_file: Option<String>,
+    repair_file: Option<String>,
+    edit_costs: EditCosts,
+    stream_file: Option<String>,
+    recovery: Recovery,
+    delimiters: DelimiterSet,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let (mut check_file, mut repair_file, mut edit_costs) = (None, None, EditCosts::default());
+    let (mut stream_file, mut recovery) = (None, Recovery::SkipLine);
+    let (mut pairs, mut symmetric, mut escape, mut ignored) = (None, Vec::new(), None, Vec::new());
+    let mut ignore_others = false;
+    while let Some(arg) = args.next() {
+        if arg == "--ignore-others" {
+            ignore_others = true;
+            continue;
+        }
+        let value = args.next().ok_or(format!("Missing value for {}", arg))?;
+        match arg.as_str() {
+            "--check" => check_file = Some(value),
+            "--repair" => repair_file = Some(value),
+            "--edit-costs" => edit_costs = value.parse()?,
+            "--stream" => stream_file = Some(value),
+            "--recovery" => recovery = value.parse()?,
+            "--pairs" => {
+                let chars = value.chars().collect::<Vec<_>>();
+                if chars.len() % 2 != 0 {
+                    return Err(format!("Unpaired delimiters {}", value));
+                }
+                pairs = Some(
+                    chars
+                        .chunks(2)
+                        .map(|pair| (pair[0], pair[1]))
+                        .collect::<Vec<_>>(),
+                );
+            }
+            "--symmetric" => symmetric = value.chars().map(|c| (c, c)).collect(),
+            "--escape" => {
+                let mut chars = value.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => escape = Some(c),
+                    _ => return Err(format!("Invalid escape character {}", value)),
+                }
+            }
+            "--ignore" => ignored = value.chars().collect(),
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+
+    let mut all_pairs =
+        pairs.unwrap_or_else(|| vec![('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')]);
+    all_pairs.extend(symmetric);
+    let mut delimiters = DelimiterSet::new(&all_pairs)?.with_ignored(&ignored)?;
+    if let Some(escape) = escape {
+        delimiters = delimiters.with_escape(escape)?;
+    }
+    if ignore_others {
+        delimiters = delimiters.ignoring_others();
+    }
+    Ok(Options {
+        check_file,
+        repair_file,
+        edit_costs,
+        stream_file,
+        recovery,
+        delimiters,
+    })
+}
+
+fn part_1<U>(lines: &[U], delimiters: &DelimiterSet) -> usize
 where
-    U: AsRef<[Bracket]>,
+    U: AsRef<[Token]>,
 {
     lines
         .iter()
         .map(|line| validate(line.as_ref()))
         .fold(0, |acc, res| match res {
             Valid => acc,
-            Incomplete { .. } => acc,
-            Corrupted { found, .. } => acc + illegal_bracket_value(&found),
+            Incomplete { .. } | IllegalCharacter { .. } => acc,
+            Corrupted { found, .. } => acc + illegal_bracket_value(delimiters.close_char(found)),
         })
 }
 
-fn illegal_bracket_value(bracket: &BracketType) -> usize {
-    match bracket {
-        Round => 3,
-        Square => 57,
-        Curly => 1197,
-        Angle => 25137,
+/// Syntax error score of a closing bracket (0 for delimiters which are not part of the puzzle).
+fn illegal_bracket_value(close: char) -> usize {
+    match close {
+        ')' => 3,
+        ']' => 57,
+        '}' => 1197,
+        '>' => 25137,
+        _ => 0,
     }
 }
 
-fn part_2<U>(lines: &[U]) -> Result<usize, String>
+fn part_2<U>(lines: &[U], delimiters: &DelimiterSet) -> Result<usize, String>
 where
-    U: AsRef<[Bracket]>,
+    U: AsRef<[Token]>,
 {
     let completion_scores = lines
         .iter()
         .filter_map(|line| match validate(line.as_ref()) {
-            Incomplete { missing } => Some(completion_value(&missing)),
+            Incomplete { pending } => {
+                let missing = pending.iter().map(|opener| opener.delimiter);
+                Some(completion_value(&missing.collect::<Vec<_>>(), delimiters))
+            }
             _ => None,
         })
         .sorted()
         .collect::<Vec<_>>();
     let winner_index = completion_scores.len() / 2;
-    completion_scores
-        .get(winner_index)
-        .map(|res| *res)
-        .ok_or(format!(
-            "Wrong number of incomplete lines: {}",
-            completion_scores.len()
-        ))
+    completion_scores.get(winner_index).copied().ok_or(format!(
+        "Wrong number of incomplete lines: {}",
+        completion_scores.len()
+    ))
 }
 
-fn completion_value(completion: &[BracketType]) -> usize {
+/// Completion score (delimiters which are not part of the puzzle count as 0).
+fn completion_value(completion: &[DelimiterType], delimiters: &DelimiterSet) -> usize {
     completion.iter().fold(0, |acc, next| {
-        let next_value = match next {
-            Round => 1,
-            Square => 2,
-            Curly => 3,
-            Angle => 4,
+        let next_value = match delimiters.close_char(*next) {
+            ')' => 1,
+            ']' => 2,
+            '}' => 3,
+            '>' => 4,
+            _ => 0,
         };
         acc * 5 + next_value
     })
 }
 
-fn parse_input(input: &str) -> Result<Vec<Vec<Bracket>>, String> {
+fn parse_input(input: &str, delimiters: &DelimiterSet) -> Result<Vec<Vec<Token>>, String> {
     input
         .lines()
-        .map(|line| {
-            line.chars()
-                .map(|c| match c {
-                    '(' => Ok(Open(Round)),
-                    '[' => Ok(Open(Square)),
-                    '{' => Ok(Open(Curly)),
-                    '<' => Ok(Open(Angle)),
-                    ')' => Ok(Close(Round)),
-                    ']' => Ok(Close(Square)),
-                    '}' => Ok(Close(Curly)),
-                    '>' => Ok(Close(Angle)),
-                    _ => Err(format!("Illegal character {}", c)),
-                })
-                .collect::<Result<Vec<Bracket>, String>>()
+        .enumerate()
+        .map(|(index, line)| {
+            delimiters.tokenize(line).map_err(|illegal| {
+                let end = line.chars().count();
+                let message = describe(&illegal, end, delimiters).unwrap_or_default();
+                format!("Line {}: {}", index + 1, message)
+            })
         })
-        .collect::<Result<Vec<Vec<Bracket>>, String>>()
+        .collect::<Result<Vec<Vec<Token>>, String>>()
 }
 
-enum Bracket {
-    Open(BracketType),
-    Close(BracketType),
-}
-
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
-enum BracketType {
-    Round,
-    Square,
-    Curly,
-    Angle,
+/// Validates a line of text, which may contain illegal characters.
+fn validate_line(line: &str, delimiters: &DelimiterSet) -> ValidationResult {
+    match delimiters.tokenize(line) {
+        Ok(tokens) => validate(&tokens),
+        Err(illegal) => illegal,
+    }
 }
 
-fn validate(line: &[Bracket]) -> ValidationResult {
-    let mut stack: Vec<BracketType> = Vec::new();
+fn validate(line: &[Token]) -> ValidationResult {
+    let mut stack: Vec<Opener> = Vec::new();
 
-    for bracket in line {
+    for (column, bracket) in line {
         match bracket {
-            Open(bracket_type) => stack.push(*bracket_type),
+            Open(delimiter) => stack.push(Opener { delimiter: *delimiter, column: *column }),
             Close(found) => match stack.pop() {
-                None => return Corrupted { expected: None, found: *found },
-                Some(expected) if expected != *found => {
-                    return Corrupted { expected: Some(expected), found: *found };
+                None => return Corrupted { expected: None, found: *found, column: *column },
+                Some(expected) if expected.delimiter != *found => {
+                    return Corrupted { expected: Some(expected), found: *found, column: *column };
                 }
                 _ => {}
             },
@@ -130,25 +275,44 @@ fn validate(line: &[Bracket]) -> ValidationResult {
         Valid
     } else {
         stack.reverse();
-        Incomplete { missing: stack }
+        Incomplete { pending: stack }
     }
 }
 
+/// Opening delimiter which has not been closed yet.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+struct Opener {
+    delimiter: DelimiterType,
+    column: usize,
+}
+
 #[derive(PartialEq, Eq, Debug)]
 enum ValidationResult {
     Valid,
+    /// The line ends before the pending openers are closed (innermost first).
     Incomplete {
-        missing: Vec<BracketType>,
+        pending: Vec<Opener>,
     },
+    /// The closing delimiter `found` at `column` doesn't match the innermost pending opener
+    /// (`expected`), if any.
     Corrupted {
-        expected: Option<BracketType>,
-        found: BracketType,
+        expected: Option<Opener>,
+        found: DelimiterType,
+        column: usize,
+    },
+    /// Character at `column` which is not allowed by the delimiter set, `None` for invalid UTF-8
+    /// (only found by [`StreamValidator`]). Whole lines stop at the first one, streams resume
+    /// after it.
+    IllegalCharacter {
+        character: Option<char>,
+        column: usize,
     },
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::repair::Edit;
 
     static TEST_INPUT: &str = "[({(<(())[]>[[{[]{<()<>>
 [(()[<>])]({[<{<<[]>>(
@@ -163,8 +327,9 @@ mod tests {
 
     #[test]
     fn test_part_1() -> Result<(), String> {
-        let lines = parse_input(TEST_INPUT)?;
-        let actual = part_1(&lines);
+        let delimiters = DelimiterSet::puzzle();
+        let lines = parse_input(TEST_INPUT, &delimiters)?;
+        let actual = part_1(&lines, &delimiters);
         let expected: usize = 26397;
         assert_eq!(expected, actual);
         Ok(())
@@ -172,10 +337,286 @@ mod tests {
 
     #[test]
     fn test_part_2() -> Result<(), String> {
-        let lines = parse_input(TEST_INPUT)?;
-        let actual = part_2(&lines)?;
+        let delimiters = DelimiterSet::puzzle();
+        let lines = parse_input(TEST_INPUT, &delimiters)?;
+        let actual = part_2(&lines, &delimiters)?;
         let expected: usize = 288957;
         assert_eq!(expected, actual);
         Ok(())
     }
+
+    #[test]
+    fn test_delimiter_set() -> Result<(), String> {
+        let delimiters = DelimiterSet::new(&[('(', ')'), ('"', '"'), ('{', '}')])?
+            .with_escape('\\')?
+            .with_ignored(&[' ', ':', ','])?;
+        let validate_line = |line: &str| validate_line(line, &delimiters);
+        assert_eq!(Valid, validate_line(r#"{"a": ("b)", "c\"}"), "d": {}}"#));
+        assert_eq!(Valid, validate_line(r#"(\))"#));
+        assert_eq!(
+            Corrupted {
+                expected: Some(Opener { delimiter: 2, column: 0 }),
+                found: 0,
+                column: 5
+            },
+            validate_line(r#"{"}" )"#)
+        );
+        assert_eq!(
+            Incomplete {
+                pending: vec![
+                    Opener { delimiter: 1, column: 1 },
+                    Opener { delimiter: 2, column: 0 }
+                ]
+            },
+            validate_line(r#"{"abc"#)
+        );
+        assert_eq!(
+            IllegalCharacter { character: Some('x'), column: 1 },
+            validate_line(r#"{x}"#)
+        );
+        assert_eq!(
+            Ok(vec![(0, Open(0)), (3, Close(0))]),
+            delimiters.clone().ignoring_others().tokenize("(xy)")
+        );
+        assert_eq!(
+            Err("Line 2: column 3: illegal character 'x'".to_owned()),
+            parse_input("()\n{(x)}", &delimiters)
+        );
+
+        assert!(DelimiterSet::new(&[('(', ')'), (')', '(')]).is_err());
+        assert!(DelimiterSet::puzzle().with_escape('<').is_err());
+        assert!(DelimiterSet::puzzle().with_ignored(&['a', 'a']).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_diagnostics() -> Result<(), String> {
+        let delimiters = DelimiterSet::puzzle();
+        let diagnose =
+            |line: &str| render_diagnostic(line, &validate_line(line, &delimiters), &delimiters);
+        let expected = "\
+{([(<{}[<>[]}>{[]{[(<()>
+       -    ^
+column 13: expected ']' to close '[' from column 8, found '}'";
+        assert_eq!(
+            Some(expected.to_owned()),
+            diagnose("{([(<{}[<>[]}>{[]{[(<()>")
+        );
+        let expected = "\
+<>)
+  ^
+column 3: found ')' but no delimiter is open";
+        assert_eq!(Some(expected.to_owned()), diagnose("<>)"));
+        let expected = "\
+[({(<(())[]>[[{[]{<()<>>
+----        ---  -      ^
+column 25: expected '}}]])})]' to close the pending delimiters";
+        assert_eq!(
+            Some(expected.to_owned()),
+            diagnose("[({(<(())[]>[[{[]{<()<>>")
+        );
+        let expected = "\
+[(a)]
+  ^
+column 3: illegal character 'a'";
+        assert_eq!(Some(expected.to_owned()), diagnose("[(a)]"));
+        assert_eq!(None, diagnose("[<>({}){}[([])<>]]"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_repair() {
+        let delimiters = DelimiterSet::puzzle();
+        let repair_line = |line: &str, costs: &EditCosts| {
+            let repair = repair(&delimiters.tokenize(line).unwrap(), line.len(), costs);
+            (repair.cost, apply(line, &repair.edits, &delimiters))
+        };
+        let unit = EditCosts::default();
+        assert_eq!(
+            (0, "[<>({}){}[([])<>]]".to_owned()),
+            repair_line("[<>({}){}[([])<>]]", &unit)
+        );
+        assert_eq!((1, "{()()()}".to_owned()), repair_line("{()()()>", &unit));
+        assert_eq!((1, "[]".to_owned()), repair_line("[", &unit));
+        assert_eq!((1, "()".to_owned()), repair_line(")", &unit));
+        assert_eq!((2, "[][]".to_owned()), repair_line("][", &unit));
+
+        let no_delete = EditCosts { insert: 1, delete: 10, replace: 10 };
+        assert_eq!(
+            (2, "{()()()<>}".to_owned()),
+            repair_line("{()()()>", &no_delete)
+        );
+        let cheap_delete = EditCosts { insert: 10, delete: 1, replace: 10 };
+        assert_eq!(
+            (2, "()()()".to_owned()),
+            repair_line("{()()()>", &cheap_delete)
+        );
+
+        // Extreme costs saturate instead of overflowing.
+        let huge_insert = "18446744073709551615,1,1".parse::<EditCosts>().unwrap();
+        assert_eq!((1, "()".to_owned()), repair_line("((", &huge_insert));
+        let huge = EditCosts { insert: u64::MAX, delete: u64::MAX, replace: u64::MAX };
+        assert_eq!(u64::MAX, repair_line("(((]", &huge).0);
+        assert_eq!((0, "()".to_owned()), repair_line("()", &huge));
+
+        let repair = repair(&delimiters.tokenize("(]").unwrap(), 2, &no_delete);
+        assert_eq!(
+            vec![
+                Edit::Insert { column: 1, bracket: Open(1) },
+                Edit::Insert { column: 2, bracket: Close(0) }
+            ],
+            repair.edits
+        );
+    }
+
+    #[test]
+    fn test_stream() -> Result<(), String> {
+        let delimiters = DelimiterSet::puzzle();
+        let validate_stream = |input: &str, chunk_size: usize, recovery: Recovery| {
+            let mut validator = StreamValidator::new(&delimiters, recovery);
+            let mut issues = Vec::new();
+            for chunk in input.as_bytes().chunks(chunk_size) {
+                issues.extend(validator.feed(chunk));
+            }
+            issues.extend(validator.finish());
+            issues
+        };
+        let opener = |delimiter, column| Opener { delimiter, column };
+
+        // Same results as validating whole lines, whatever the chunks.
+        let expected = TEST_INPUT
+            .lines()
+            .enumerate()
+            .map(|(line, text)| (line, validate(&delimiters.tokenize(text).unwrap())))
+            .filter(|(_, result)| *result != Valid)
+            .collect::<Vec<_>>();
+        for chunk_size in [1, 3, 7, 1000] {
+            let issues = validate_stream(TEST_INPUT, chunk_size, Recovery::SkipLine);
+            let issues = issues.into_iter().map(|issue| (issue.line, issue.result));
+            assert_eq!(expected, issues.collect::<Vec<_>>());
+        }
+
+        let corrupted = |expected, found, column| Corrupted { expected, found, column };
+        let results = |input, recovery| {
+            let issues = validate_stream(input, 2, recovery);
+            issues
+                .into_iter()
+                .map(|issue| issue.result)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![
+                corrupted(Some(opener(1, 1)), 0, 2),
+                Incomplete { pending: vec![opener(0, 0)] }
+            ],
+            results("([)]", Recovery::IgnoreCloser)
+        );
+        assert_eq!(
+            vec![corrupted(Some(opener(1, 1)), 0, 2)],
+            results("([)]\n([)]", Recovery::Stop)
+        );
+        assert_eq!(
+            vec![
+                corrupted(Some(opener(1, 1)), 0, 2),
+                corrupted(None, 1, 3),
+                Incomplete { pending: vec![opener(2, 4)] }
+            ],
+            results("([)]{", Recovery::PopToMatch)
+        );
+
+        // Same lines as `str::lines`, with `\r\n` line ends.
+        let crlf_input = TEST_INPUT.replace('\n', "\r\n") + "\r\n";
+        for chunk_size in [1, 2, 1000] {
+            let issues = validate_stream(&crlf_input, chunk_size, Recovery::SkipLine);
+            let issues = issues.into_iter().map(|issue| (issue.line, issue.result));
+            assert_eq!(expected, issues.collect::<Vec<_>>());
+        }
+        assert_eq!(
+            vec![
+                IllegalCharacter { character: Some('\r'), column: 2 },
+                IllegalCharacter { character: Some('\r'), column: 4 }
+            ],
+            results("()\r(\r)\r\n[]", Recovery::IgnoreCloser)
+        );
+
+        // Illegal characters are reported, and the validation resumes.
+        let illegal = |character, column| IllegalCharacter { character, column };
+        assert_eq!(
+            vec![
+                illegal(Some('x'), 1),
+                Incomplete { pending: vec![opener(0, 0)] }
+            ],
+            results("(x[]\n()", Recovery::IgnoreCloser)
+        );
+        assert_eq!(
+            vec![illegal(Some('x'), 1), corrupted(None, 1, 0)],
+            results("(x\n]", Recovery::SkipLine)
+        );
+
+        // Characters cut short by another character or by the end of the stream, whatever the
+        // chunks: only their start is invalid.
+        let cut_short: [(&[u8], _); 5] = [
+            (b"(\xE2)", vec![illegal(None, 1)]),
+            (b"(\xE2\x82)", vec![illegal(None, 1)]),
+            (b"(\xE0\x80)", vec![illegal(None, 1), illegal(None, 2)]),
+            (
+                b"(\xF0\x90\x8D)[",
+                vec![illegal(None, 1), Incomplete { pending: vec![opener(1, 3)] }],
+            ),
+            (
+                b"(\xE2",
+                vec![illegal(None, 1), Incomplete { pending: vec![opener(0, 0)] }],
+            ),
+        ];
+        for (bytes, expected) in cut_short {
+            for chunk_size in [1, 2, 3, 1000] {
+                let mut validator = StreamValidator::new(&delimiters, Recovery::IgnoreCloser);
+                let mut issues = Vec::new();
+                for chunk in bytes.chunks(chunk_size) {
+                    issues.extend(validator.feed(chunk));
+                }
+                issues.extend(validator.finish());
+                let results = issues.into_iter().map(|issue| issue.result);
+                assert_eq!(expected, results.collect::<Vec<_>>(), "{:?}", bytes);
+            }
+        }
+
+        // Characters split between chunks.
+        let delimiters = DelimiterSet::new(&[('«', '»')])?;
+        let mut validator = StreamValidator::new(&delimiters, Recovery::SkipLine);
+        let bytes = "««»".as_bytes();
+        for byte in bytes {
+            assert_eq!(Vec::<Issue>::new(), validator.feed(&[*byte]));
+        }
+        let issues = validator.finish();
+        assert_eq!(
+            vec![Issue {
+                line: 0,
+                end: 3,
+                result: Incomplete { pending: vec![opener(0, 0)] }
+            }],
+            issues
+        );
+        // Invalid UTF-8, also when split between chunks.
+        let mut validator = StreamValidator::new(&delimiters, Recovery::IgnoreCloser);
+        assert_eq!(Vec::<Issue>::new(), validator.feed(&bytes[..1]));
+        let issues = validator.feed(&[b'x', 0xFF, bytes[0], bytes[1], bytes[2]]);
+        let issues = issues
+            .into_iter()
+            .map(|issue| issue.result)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![illegal(None, 0), illegal(Some('x'), 1), illegal(None, 2)],
+            issues
+        );
+        let issues = validator.finish();
+        assert_eq!(
+            vec![illegal(None, 4), Incomplete { pending: vec![opener(0, 3)] }],
+            issues
+                .into_iter()
+                .map(|issue| issue.result)
+                .collect::<Vec<_>>()
+        );
+        Ok(())
+    }
 }
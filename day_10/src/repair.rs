@@ -0,0 +1,196 @@
+use crate::delimiters::Bracket::{Close, Open};
+use crate::delimiters::{Bracket, DelimiterSet, Token};
+use std::str::FromStr;
+
+/// Cost of each kind of edit.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct EditCosts {
+    pub insert: u64,
+    pub delete: u64,
+    pub replace: u64,
+}
+
+impl Default for EditCosts {
+    fn default() -> Self {
+        EditCosts { insert: 1, delete: 1, replace: 1 }
+    }
+}
+
+/// `INSERT,DELETE,REPLACE`, e.g. `1,2,1`.
+impl FromStr for EditCosts {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let costs = s
+            .split(',')
+            .map(|cost| {
+                cost.parse::<u64>()
+                    .map_err(|_| format!("Invalid cost {}", cost))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        match costs[..] {
+            [insert, delete, replace] => Ok(EditCosts { insert, delete, replace }),
+            _ => Err(format!("Expected 3 edit costs, got {}", s)),
+        }
+    }
+}
+
+/// Edit of a line. Columns refer to the original line; insertions go before `column` (which is
+/// the length of the line to append).
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Edit {
+    Insert { column: usize, bracket: Bracket },
+    Delete { column: usize },
+    Replace { column: usize, bracket: Bracket },
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub struct Repair {
+    /// Total cost of the edits, saturating at `u64::MAX`.
+    pub cost: u64,
+    /// Sorted by column; insertions at the same column are in order.
+    pub edits: Vec<Edit>,
+}
+
+/// How the first token of a range is made balanced, in an optimal repair.
+#[derive(Clone, Copy)]
+enum Choice {
+    Empty,
+    Delete,
+    /// Insert the matching delimiter: before it for a closing delimiter, at the end of the range
+    /// for an opening one.
+    Insert,
+    /// Pair it with the token at that index, as `bracket`s.
+    Pair(usize, Bracket, Bracket),
+}
+
+/// Minimum-cost edits making the delimiters of a line balanced (interval dynamic programming,
+/// cubic in the number of delimiters). `length` is the length of the line, in characters.
+/// Insertions are preferred over deletions of the same cost, so that incomplete lines get
+/// completed.
+pub fn repair(line: &[Token], length: usize, costs: &EditCosts) -> Repair {
+    let n = line.len();
+    // cost[i][j] and choice[i][j]: tokens i..j (excluded).
+    let mut cost = vec![vec![0; n + 1]; n + 1];
+    let mut choice = vec![vec![Choice::Empty; n + 1]; n + 1];
+    for length in 1..=n {
+        for i in 0..=n - length {
+            let j = i + length;
+            let mut best = (costs.insert.saturating_add(cost[i + 1][j]), Choice::Insert);
+            let deleted = costs.delete.saturating_add(cost[i + 1][j]);
+            if deleted < best.0 {
+                best = (deleted, Choice::Delete);
+            }
+            for k in i + 1..j {
+                let (pair_cost, open, close) = pair(line[i].1, line[k].1, costs);
+                let total = pair_cost
+                    .saturating_add(cost[i + 1][k])
+                    .saturating_add(cost[k + 1][j]);
+                if total < best.0 {
+                    best = (total, Choice::Pair(k, open, close));
+                }
+            }
+            cost[i][j] = best.0;
+            choice[i][j] = best.1;
+        }
+    }
+
+    let mut edits = Vec::new();
+    collect_edits(line, length, &choice, (0, n), &mut edits);
+    // Stable: keeps the order of insertions at the same column.
+    edits.sort_by_key(|edit| match edit {
+        Edit::Insert { column, .. } | Edit::Delete { column } | Edit::Replace { column, .. } => {
+            *column
+        }
+    });
+    Repair { cost: cost[0][n], edits }
+}
+
+/// Cheapest way to make `first` and `second` a matching pair, with the resulting brackets.
+fn pair(first: Bracket, second: Bracket, costs: &EditCosts) -> (u64, Bracket, Bracket) {
+    let delimiter_of = |bracket| match bracket {
+        Open(delimiter) | Close(delimiter) => delimiter,
+    };
+    [delimiter_of(first), delimiter_of(second)]
+        .iter()
+        .map(|&delimiter| {
+            let (open, close) = (Open(delimiter), Close(delimiter));
+            let replaced = (first != open) as u64 + (second != close) as u64;
+            (replaced.saturating_mul(costs.replace), open, close)
+        })
+        .min_by_key(|(cost, _, _)| *cost)
+        .unwrap()
+}
+
+fn collect_edits(
+    line: &[Token],
+    length: usize,
+    choice: &[Vec<Choice>],
+    (i, j): (usize, usize),
+    edits: &mut Vec<Edit>,
+) {
+    if i == j {
+        return;
+    }
+    let (column, bracket) = line[i];
+    match choice[i][j] {
+        Choice::Empty => {}
+        Choice::Delete => {
+            edits.push(Edit::Delete { column });
+            collect_edits(line, length, choice, (i + 1, j), edits);
+        }
+        Choice::Insert => match bracket {
+            Open(delimiter) => {
+                // After the insertions of the range, which close inner delimiters.
+                collect_edits(line, length, choice, (i + 1, j), edits);
+                let end = line.get(j).map_or(length, |(column, _)| *column);
+                edits.push(Edit::Insert { column: end, bracket: Close(delimiter) });
+            }
+            Close(delimiter) => {
+                edits.push(Edit::Insert { column, bracket: Open(delimiter) });
+                collect_edits(line, length, choice, (i + 1, j), edits);
+            }
+        },
+        Choice::Pair(k, open, close) => {
+            if bracket != open {
+                edits.push(Edit::Replace { column, bracket: open });
+            }
+            if line[k].1 != close {
+                edits.push(Edit::Replace { column: line[k].0, bracket: close });
+            }
+            collect_edits(line, length, choice, (i + 1, k), edits);
+            collect_edits(line, length, choice, (k + 1, j), edits);
+        }
+    }
+}
+
+/// The line with the edits (sorted by column) applied.
+pub fn apply(line: &str, edits: &[Edit], delimiters: &DelimiterSet) -> String {
+    let to_char = |bracket| match bracket {
+        Open(delimiter) => delimiters.open_char(delimiter),
+        Close(delimiter) => delimiters.close_char(delimiter),
+    };
+    let mut edits = edits.iter().peekable();
+    let length = line.chars().count();
+    let mut repaired = String::new();
+    for (column, c) in line.chars().chain(std::iter::once('\0')).enumerate() {
+        let mut kept = Some(c);
+        while let Some(edit) = edits.peek() {
+            match **edit {
+                Edit::Insert { column: at, bracket } if at == column => {
+                    repaired.push(to_char(bracket))
+                }
+                Edit::Delete { column: at } if at == column => kept = None,
+                Edit::Replace { column: at, bracket } if at == column => {
+                    kept = Some(to_char(bracket))
+                }
+                _ => break,
+            }
+            edits.next();
+        }
+        if column < length {
+            repaired.extend(kept);
+        }
+    }
+    repaired
+}
@@ -0,0 +1,246 @@
+use crate::delimiters::Bracket::{Close, Open};
+use crate::delimiters::{DelimiterSet, DelimiterType, Tokenizer};
+use crate::{Opener, ValidationResult};
+use std::str::FromStr;
+
+/// What to do with the rest of a line after a corrupted closing delimiter or an illegal character.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Recovery {
+    /// Ignore the rest of the line, as in the puzzle.
+    SkipLine,
+    /// Drop the closing delimiter or the character, and keep validating with the same pending
+    /// openers.
+    IgnoreCloser,
+    /// Close the innermost pending opener of the same type (dropping the openers within it), or
+    /// drop the closing delimiter if there is none. Illegal characters are dropped.
+    PopToMatch,
+    /// Stop validating: the rest of the stream is ignored.
+    Stop,
+}
+
+impl FromStr for Recovery {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip-line" => Ok(Recovery::SkipLine),
+            "ignore" => Ok(Recovery::IgnoreCloser),
+            "pop" => Ok(Recovery::PopToMatch),
+            "stop" => Ok(Recovery::Stop),
+            _ => Err(format!("Unknown recovery policy {}", s)),
+        }
+    }
+}
+
+/// Invalid line found in a stream. `line` and `end` (the length of the line so far) start at 0.
+#[derive(PartialEq, Eq, Debug)]
+pub struct Issue {
+    pub line: usize,
+    pub end: usize,
+    pub result: ValidationResult,
+}
+
+/// Validates lines fed as chunks of UTF-8 bytes, without keeping them in memory. Chunks may end
+/// anywhere, even in the middle of a character. Illegal characters and invalid UTF-8 are reported
+/// like corrupted delimiters.
+pub struct StreamValidator<'a> {
+    delimiters: &'a DelimiterSet,
+    recovery: Recovery,
+    tokenizer: Tokenizer<'a>,
+    stack: Vec<Opener>,
+    line: usize,
+    column: usize,
+    /// Whether the rest of the line is ignored, after a corruption.
+    skipping: bool,
+    stopped: bool,
+    /// Whether the last character is `\r`, which ends the line if `\n` follows.
+    carriage_return: bool,
+    /// Start of a character split between chunks.
+    partial: Vec<u8>,
+}
+
+impl<'a> StreamValidator<'a> {
+    pub fn new(delimiters: &'a DelimiterSet, recovery: Recovery) -> StreamValidator<'a> {
+        StreamValidator {
+            delimiters,
+            recovery,
+            tokenizer: delimiters.tokenizer(),
+            stack: Vec::new(),
+            line: 0,
+            column: 0,
+            skipping: false,
+            stopped: false,
+            carriage_return: false,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Validates the next bytes of the stream, returning the issues found in them.
+    pub fn feed(&mut self, mut chunk: &[u8]) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        // Only continuation bytes complete a split character: any other byte cuts it short.
+        while !self.partial.is_empty() {
+            match chunk.first() {
+                None => return issues,
+                Some(byte @ 0x80..=0xBF) => {
+                    self.partial.push(*byte);
+                    chunk = &chunk[1..];
+                    if self.partial.len() == utf8_width(self.partial[0]) {
+                        let partial = std::mem::take(&mut self.partial);
+                        self.push_bytes(&partial, false, &mut issues);
+                    }
+                }
+                Some(_) => {
+                    let partial = std::mem::take(&mut self.partial);
+                    self.push_bytes(&partial, true, &mut issues);
+                }
+            }
+        }
+        self.push_bytes(chunk, false, &mut issues);
+        issues
+    }
+
+    /// Ends the stream, returning the issues of its last line (if it doesn't end with a newline).
+    pub fn finish(mut self) -> Vec<Issue> {
+        let mut issues = Vec::new();
+        let partial = std::mem::take(&mut self.partial);
+        self.push_bytes(&partial, true, &mut issues);
+        if self.carriage_return {
+            self.validate_char(Some('\r'), &mut issues);
+        }
+        if self.column > 0 {
+            self.end_line(&mut issues);
+        }
+        issues
+    }
+
+    /// Validates bytes, keeping the start of a character cut by their end for the next chunk, or
+    /// reporting it as invalid if nothing can follow (`is_cut`).
+    fn push_bytes(&mut self, mut bytes: &[u8], is_cut: bool, issues: &mut Vec<Issue>) {
+        loop {
+            match std::str::from_utf8(bytes) {
+                Ok(text) => {
+                    text.chars().for_each(|c| self.push_char(Some(c), issues));
+                    return;
+                }
+                Err(err) => {
+                    let (valid, rest) = bytes.split_at(err.valid_up_to());
+                    let valid = std::str::from_utf8(valid).unwrap();
+                    valid.chars().for_each(|c| self.push_char(Some(c), issues));
+                    match err.error_len() {
+                        None if is_cut => {
+                            self.push_char(None, issues);
+                            return;
+                        }
+                        None => {
+                            self.partial = rest.to_vec();
+                            return;
+                        }
+                        Some(invalid) => {
+                            self.push_char(None, issues);
+                            bytes = &rest[invalid..];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Validates the next character, `None` for invalid UTF-8. Lines end with `\n` or `\r\n`, as
+    /// with [`str::lines`].
+    fn push_char(&mut self, c: Option<char>, issues: &mut Vec<Issue>) {
+        if self.carriage_return {
+            self.carriage_return = false;
+            if c != Some('\n') {
+                self.validate_char(Some('\r'), issues);
+            }
+        }
+        if c == Some('\r') {
+            self.carriage_return = true;
+        } else {
+            self.validate_char(c, issues);
+        }
+    }
+
+    fn validate_char(&mut self, c: Option<char>, issues: &mut Vec<Issue>) {
+        if self.stopped {
+            return;
+        }
+        if c == Some('\n') {
+            self.end_line(issues);
+            return;
+        }
+        let column = self.column;
+        self.column += 1;
+        if self.skipping {
+            return;
+        }
+        // Invalid UTF-8 is fine where any character is, e.g. in quotes.
+        let bracket = match self
+            .tokenizer
+            .push(c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        {
+            Ok(bracket) => bracket,
+            Err(_) => {
+                let result = ValidationResult::IllegalCharacter { character: c, column };
+                issues.push(Issue { line: self.line, end: self.column, result });
+                self.recover(None);
+                return;
+            }
+        };
+        match bracket {
+            None => {}
+            Some(Open(delimiter)) => self.stack.push(Opener { delimiter, column }),
+            Some(Close(found)) => match self.stack.last() {
+                Some(opener) if opener.delimiter == found => {
+                    self.stack.pop();
+                }
+                expected => {
+                    let expected = expected.copied();
+                    let result = ValidationResult::Corrupted { expected, found, column };
+                    issues.push(Issue { line: self.line, end: self.column, result });
+                    self.recover(Some(found));
+                }
+            },
+        }
+    }
+
+    /// Applies the recovery policy, after the closing delimiter `found` or an illegal character.
+    fn recover(&mut self, found: Option<DelimiterType>) {
+        match self.recovery {
+            Recovery::SkipLine => self.skipping = true,
+            Recovery::IgnoreCloser => {}
+            Recovery::PopToMatch => {
+                let matching = self.stack.iter().rposition(|o| Some(o.delimiter) == found);
+                if let Some(index) = matching {
+                    self.stack.truncate(index);
+                }
+            }
+            Recovery::Stop => self.stopped = true,
+        }
+    }
+
+    fn end_line(&mut self, issues: &mut Vec<Issue>) {
+        if !self.skipping && !self.stopped && !self.stack.is_empty() {
+            let mut pending = std::mem::take(&mut self.stack);
+            pending.reverse();
+            let result = ValidationResult::Incomplete { pending };
+            issues.push(Issue { line: self.line, end: self.column, result });
+        }
+        self.stack.clear();
+        self.tokenizer = self.delimiters.tokenizer();
+        self.line += 1;
+        self.column = 0;
+        self.skipping = false;
+    }
+}
+
+/// Length of a UTF-8 character from its first byte (1 for invalid bytes, which are then reported).
+fn utf8_width(first: u8) -> usize {
+    match first {
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 1,
+    }
+}
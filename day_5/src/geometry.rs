@@ -0,0 +1,207 @@
+use std::cmp::{max, min};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+/// Largest absolute value of a coordinate. Products of two differences of coordinates, as
+/// computed along the lines, then stay within an `i64`.
+pub const MAX_COORDINATE: i64 = 1_000_000_000;
+
+/// A line segment from `(x1, y1)` to `(x2, y2)`, both ends included, with coordinates within
+/// [`MAX_COORDINATE`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Line {
+    pub x1: i64,
+    pub y1: i64,
+    pub x2: i64,
+    pub y2: i64,
+}
+
+impl Line {
+    pub fn start(&self) -> Point {
+        Point { x: self.x1, y: self.y1 }
+    }
+
+    pub fn end(&self) -> Point {
+        Point { x: self.x2, y: self.y2 }
+    }
+
+    /// Displacement from the start to the end of the line.
+    pub fn deltas(&self) -> (i64, i64) {
+        (self.x2 - self.x1, self.y2 - self.y1)
+    }
+
+    pub fn is_horiz_or_vert(&self) -> bool {
+        self.x1 == self.x2 || self.y1 == self.y2
+    }
+
+    pub fn is_horiz_vert_or_45(&self) -> bool {
+        let (dx, dy) = self.deltas();
+        self.is_horiz_or_vert() || dx.abs() == dy.abs()
+    }
+
+    pub fn bounding_box(&self) -> BoundingBox {
+        BoundingBox::new(self.start(), self.end())
+    }
+
+    /// Grid points of the line which lie inside `region`, in order from the start of the line.
+    pub fn lattice_points_within(&self, region: &BoundingBox) -> Vec<Point> {
+        let (dx, dy) = self.deltas();
+        let steps = gcd(dx.unsigned_abs(), dy.unsigned_abs()) as i64;
+        if steps == 0 {
+            return Some(self.start())
+                .filter(|p| region.contains(*p))
+                .into_iter()
+                .collect();
+        }
+        let (step_x, step_y) = (dx / steps, dy / steps);
+        let (first_x, last_x) = step_range(self.x1, step_x, region.min.x, region.max.x);
+        let (first_y, last_y) = step_range(self.y1, step_y, region.min.y, region.max.y);
+        (max(0, max(first_x, first_y))..=min(steps, min(last_x, last_y)))
+            .map(|i| Point { x: self.x1 + i * step_x, y: self.y1 + i * step_y })
+            .collect()
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        orientation(self.start(), self.end(), point) == Orientation::Collinear
+            && self.bounding_box().contains(point)
+    }
+
+    /// Whether the two lines share at least one point, which need not be a grid point.
+    pub fn intersects(&self, other: &Line) -> bool {
+        if !self.bounding_box().intersects(&other.bounding_box()) {
+            return false;
+        }
+        let (p1, p2) = (self.start(), self.end());
+        let (q1, q2) = (other.start(), other.end());
+        // General case: the ends of each line are on both sides of the other line.
+        (orientation(p1, p2, q1) != orientation(p1, p2, q2)
+            && orientation(q1, q2, p1) != orientation(q1, q2, p2))
+            // Special cases: an end of a line lies on the other line.
+            || self.contains(q1)
+            || self.contains(q2)
+            || other.contains(p1)
+            || other.contains(p2)
+    }
+}
+
+/// Range of steps `i` such that `low <= start + i * step <= high`, or an empty range if there
+/// are none.
+pub fn step_range(start: i64, step: i64, low: i64, high: i64) -> (i64, i64) {
+    match step.signum() {
+        0 if (low..=high).contains(&start) => (i64::MIN, i64::MAX),
+        0 => (1, 0),
+        1 => (
+            -(start - low).div_euclid(step),
+            (high - start).div_euclid(step),
+        ),
+        _ => (
+            -(high - start).div_euclid(-step),
+            (start - low).div_euclid(-step),
+        ),
+    }
+}
+
+/// Smallest axis-aligned rectangle containing some points, both corners included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoundingBox {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl BoundingBox {
+    /// The rectangle having `a` and `b` as opposite corners.
+    pub fn new(a: Point, b: Point) -> BoundingBox {
+        BoundingBox {
+            min: Point { x: min(a.x, b.x), y: min(a.y, b.y) },
+            max: Point { x: max(a.x, b.x), y: max(a.y, b.y) },
+        }
+    }
+
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min: Point {
+                x: min(self.min.x, other.min.x),
+                y: min(self.min.y, other.min.y),
+            },
+            max: Point {
+                x: max(self.max.x, other.max.x),
+                y: max(self.max.y, other.max.y),
+            },
+        }
+    }
+
+    /// Number of grid points in the rectangle (saturating), which may not fit in a `usize`.
+    pub fn area(&self) -> u128 {
+        let width = (self.max.x as i128 - self.min.x as i128 + 1) as u128;
+        let height = (self.max.y as i128 - self.min.y as i128 + 1) as u128;
+        width.saturating_mul(height)
+    }
+
+    /// Number of columns covered by the rectangle.
+    pub fn width(&self) -> usize {
+        (self.max.x - self.min.x + 1) as usize
+    }
+
+    /// Number of rows covered by the rectangle.
+    pub fn height(&self) -> usize {
+        (self.max.y - self.min.y + 1) as usize
+    }
+
+    pub fn contains(&self, point: Point) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x) && (self.min.y..=self.max.y).contains(&point.y)
+    }
+
+    /// Whether the line has at least one point, which need not be a grid point, inside the
+    /// rectangle.
+    pub fn intersects_line(&self, line: &Line) -> bool {
+        let corners = [
+            self.min,
+            Point { x: self.max.x, y: self.min.y },
+            self.max,
+            Point { x: self.min.x, y: self.max.y },
+        ];
+        self.contains(line.start())
+            || (0..4).any(|i| {
+                let (a, b) = (corners[i], corners[(i + 1) % 4]);
+                line.intersects(&Line { x1: a.x, y1: a.y, x2: b.x, y2: b.y })
+            })
+    }
+
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min.x <= other.max.x
+            && other.min.x <= self.max.x
+            && self.min.y <= other.max.y
+            && other.min.y <= self.max.y
+    }
+}
+
+/// Turn direction when going from `a` to `b` then `c`, with the x axis pointing right and the
+/// y axis pointing up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Clockwise,
+    CounterClockwise,
+    Collinear,
+}
+
+pub fn orientation(a: Point, b: Point, c: Point) -> Orientation {
+    let cross =
+        (b.x - a.x) as i128 * (c.y - a.y) as i128 - (b.y - a.y) as i128 * (c.x - a.x) as i128;
+    match cross.signum() {
+        1 => Orientation::CounterClockwise,
+        -1 => Orientation::Clockwise,
+        _ => Orientation::Collinear,
+    }
+}
+
+pub fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
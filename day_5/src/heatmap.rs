@@ -0,0 +1,130 @@
+use crate::geometry::{BoundingBox, Line, Point};
+use crate::raster::{line_points_within, Rasterization};
+
+/// Largest number of points in a heatmap, to keep its memory use reasonable.
+const MAX_HEATMAP_AREA: u128 = 1 << 24;
+
+/// Number of vent lines covering each point of a rectangular region.
+pub struct Heatmap {
+    region: BoundingBox,
+    /// Row-major counts, starting from the top-left corner of the region.
+    counts: Vec<usize>,
+}
+
+impl Heatmap {
+    /// Builds the heatmap of the given lines, cropped to `region` if specified, or covering all
+    /// the lines otherwise. Fails if the region is too large.
+    pub fn new(
+        vent_lines: &[Line],
+        rasterization: Rasterization,
+        region: Option<BoundingBox>,
+    ) -> Result<Heatmap, String> {
+        let region = region
+            .or_else(|| {
+                vent_lines
+                    .iter()
+                    .map(|line| line.bounding_box())
+                    .reduce(|acc, next| acc.union(&next))
+            })
+            .ok_or("Cannot build the heatmap of no lines")?;
+
+        if region.area() > MAX_HEATMAP_AREA {
+            return Err(format!(
+                "Heatmap region {:?} has more than {} points, crop it",
+                region, MAX_HEATMAP_AREA
+            ));
+        }
+
+        let mut heatmap = Heatmap { region, counts: vec![0; region.width() * region.height()] };
+        for line in vent_lines {
+            if !line.bounding_box().intersects(&region) {
+                continue;
+            }
+            for point in line_points_within(line, rasterization, &region)? {
+                let index = heatmap.index(point);
+                heatmap.counts[index] += 1;
+            }
+        }
+        Ok(heatmap)
+    }
+
+    fn index(&self, point: Point) -> usize {
+        (point.y - self.region.min.y) as usize * self.region.width()
+            + (point.x - self.region.min.x) as usize
+    }
+
+    /// Diagram in the same format as the puzzle: `.` where there is no line, otherwise the
+    /// number of lines (or `+` for more than 9 lines).
+    pub fn to_ascii(&self) -> String {
+        self.counts
+            .chunks(self.region.width())
+            .map(|row| {
+                row.iter()
+                    .map(|count| match count {
+                        0 => '.',
+                        1..=9 => std::char::from_digit(*count as u32, 10).unwrap(),
+                        _ => '+',
+                    })
+                    .chain(std::iter::once('\n'))
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    /// Binary greyscale PGM image, from black (no line) to white (the highest count).
+    pub fn to_pgm(&self) -> Vec<u8> {
+        let max_count = self.max_count();
+        let mut image = self.image_header("P5");
+        image.extend(
+            self.counts
+                .iter()
+                .map(|count| (count * 255 / max_count) as u8),
+        );
+        image
+    }
+
+    /// Binary colour PPM image, going through black (no line), blue, red, yellow and white (the
+    /// highest count).
+    pub fn to_ppm(&self) -> Vec<u8> {
+        let max_count = self.max_count();
+        let mut image = self.image_header("P6");
+        for count in &self.counts {
+            image.extend(&heat_colour(*count as f64 / max_count as f64));
+        }
+        image
+    }
+
+    fn max_count(&self) -> usize {
+        self.counts.iter().copied().max().unwrap_or(0).max(1)
+    }
+
+    fn image_header(&self, magic_number: &str) -> Vec<u8> {
+        format!(
+            "{}\n{} {}\n255\n",
+            magic_number,
+            self.region.width(),
+            self.region.height()
+        )
+        .into_bytes()
+    }
+}
+
+/// Colour for a heat between `0.0` and `1.0`.
+fn heat_colour(heat: f64) -> [u8; 3] {
+    const GRADIENT: [[f64; 3]; 5] = [
+        [0.0, 0.0, 0.0],
+        [0.0, 0.0, 255.0],
+        [255.0, 0.0, 0.0],
+        [255.0, 255.0, 0.0],
+        [255.0, 255.0, 255.0],
+    ];
+    let position = heat * (GRADIENT.len() - 1) as f64;
+    let index = (position.floor() as usize).min(GRADIENT.len() - 2);
+    let ratio = position - index as f64;
+    let (from, to) = (GRADIENT[index], GRADIENT[index + 1]);
+    let mut colour = [0; 3];
+    for channel in 0..3 {
+        colour[channel] = (from[channel] + (to[channel] - from[channel]) * ratio).round() as u8;
+    }
+    colour
+}
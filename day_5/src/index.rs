@@ -0,0 +1,183 @@
+use crate::geometry::{BoundingBox, Line, Point};
+use std::cmp::{max, min, Reverse};
+use std::collections::{BTreeSet, HashMap};
+
+/// Vent lines bucketed in a grid of square cells, to answer spatial queries without going
+/// through every line. Lines are considered with their exact geometry, i.e. they cover the grid
+/// points lying exactly on them.
+pub struct SpatialIndex<'a> {
+    lines: &'a [Line],
+    cell_size: i64,
+    /// Indices of the lines passing through each cell.
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl<'a> SpatialIndex<'a> {
+    /// Builds the index with cells as large as the average line, so that each line only falls
+    /// into a few cells.
+    pub fn new(lines: &'a [Line]) -> SpatialIndex<'a> {
+        let total_extent: i64 = lines
+            .iter()
+            .map(|line| {
+                let bounds = line.bounding_box();
+                max(bounds.width(), bounds.height()) as i64
+            })
+            .sum();
+        let cell_size = max(1, total_extent / max(1, lines.len() as i64));
+        SpatialIndex::with_cell_size(lines, cell_size)
+    }
+
+    pub fn with_cell_size(lines: &'a [Line], cell_size: i64) -> SpatialIndex<'a> {
+        let mut index = SpatialIndex { lines, cell_size, cells: HashMap::new() };
+        for (line_index, line) in lines.iter().enumerate() {
+            for cell in index.line_cells(line) {
+                index.cells.entry(cell).or_default().push(line_index);
+            }
+        }
+        index
+    }
+
+    /// Lines going through the given point.
+    pub fn lines_through(&self, point: Point) -> Vec<&'a Line> {
+        self.cells
+            .get(&self.cell(point))
+            .into_iter()
+            .flatten()
+            .map(|i| &self.lines[*i])
+            .filter(|line| line.contains(point))
+            .collect()
+    }
+
+    /// Lines with at least one point, which need not be a grid point, inside the region.
+    pub fn lines_intersecting(&self, region: &BoundingBox) -> Vec<&'a Line> {
+        let (min_column, min_row) = self.cell(region.min);
+        let (max_column, max_row) = self.cell(region.max);
+        let region_cells =
+            (max_column as i128 - min_column as i128 + 1) * (max_row as i128 - min_row as i128 + 1);
+        // Large regions cover mostly empty cells: go through the occupied ones instead.
+        let candidates = if region_cells > self.cells.len() as i128 {
+            self.cells
+                .iter()
+                .filter(|((column, row), _)| {
+                    (min_column..=max_column).contains(column) && (min_row..=max_row).contains(row)
+                })
+                .flat_map(|(_, line_indices)| line_indices)
+                .collect::<BTreeSet<_>>()
+        } else {
+            (min_column..=max_column)
+                .flat_map(|column| (min_row..=max_row).map(move |row| (column, row)))
+                .filter_map(|cell| self.cells.get(&cell))
+                .flatten()
+                .collect::<BTreeSet<_>>()
+        };
+        candidates
+            .into_iter()
+            .map(|i| &self.lines[*i])
+            .filter(|line| region.intersects_line(line))
+            .collect()
+    }
+
+    /// All the points covered by at least `min_overlap` lines, in (x, y) order.
+    pub fn points_with_overlap(&self, min_overlap: usize) -> Vec<Point> {
+        let mut points = Vec::new();
+        self.for_each_cell_counts(|counts| {
+            points.extend(
+                counts
+                    .iter()
+                    .filter(|(_, count)| **count >= min_overlap)
+                    .map(|(point, _)| *point),
+            )
+        });
+        points.sort_unstable();
+        points
+    }
+
+    /// The point covered by the most lines, and how many lines cover it. Ties are broken by
+    /// taking the lowest point in (x, y) order.
+    pub fn densest_point(&self) -> Option<(Point, usize)> {
+        let mut densest: Option<(Point, usize)> = None;
+        self.for_each_cell_counts(|counts| {
+            for (point, count) in counts {
+                let is_denser = densest.is_none_or(|(densest_point, densest_count)| {
+                    (*count, Reverse(*point)) > (densest_count, Reverse(densest_point))
+                });
+                if is_denser {
+                    densest = Some((*point, *count));
+                }
+            }
+        });
+        densest
+    }
+
+    /// Calls `f` with the number of lines covering each point of each non-empty cell.
+    fn for_each_cell_counts(&self, mut f: impl FnMut(&HashMap<Point, usize>)) {
+        let mut counts = HashMap::new();
+        for (cell, line_indices) in &self.cells {
+            counts.clear();
+            let bounds = self.cell_bounds(*cell);
+            for i in line_indices {
+                for point in self.lines[*i].lattice_points_within(&bounds) {
+                    *counts.entry(point).or_insert(0) += 1;
+                }
+            }
+            f(&counts);
+        }
+    }
+
+    fn cell(&self, point: Point) -> (i64, i64) {
+        (
+            point.x.div_euclid(self.cell_size),
+            point.y.div_euclid(self.cell_size),
+        )
+    }
+
+    fn cell_bounds(&self, (column, row): (i64, i64)) -> BoundingBox {
+        BoundingBox::new(
+            Point { x: column * self.cell_size, y: row * self.cell_size },
+            Point {
+                x: (column + 1) * self.cell_size - 1,
+                y: (row + 1) * self.cell_size - 1,
+            },
+        )
+    }
+
+    /// Cells the line goes through, found column by column. Within a column, the line covers
+    /// the rows between its heights at both sides of the column.
+    fn line_cells(&self, line: &Line) -> Vec<(i64, i64)> {
+        let bounds = line.bounding_box();
+        let (dx, dy) = line.deltas();
+        let (min_column, max_column) = (self.cell(bounds.min).0, self.cell(bounds.max).0);
+        let mut cells = Vec::new();
+        for column in min_column..=max_column {
+            let (y_low, y_high) = if dx == 0 {
+                (bounds.min.y, bounds.max.y)
+            } else {
+                let from = max(column * self.cell_size, bounds.min.x);
+                let to = min((column + 1) * self.cell_size, bounds.max.x);
+                // y = y1 + (x - x1) * dy / dx, rounded outwards.
+                let height = |x: i64| {
+                    let numerator =
+                        (line.y1 as i128) * (dx as i128) + ((x - line.x1) as i128) * (dy as i128);
+                    let denominator = dx as i128;
+                    let (numerator, denominator) = if denominator < 0 {
+                        (-numerator, -denominator)
+                    } else {
+                        (numerator, denominator)
+                    };
+                    (
+                        numerator.div_euclid(denominator) as i64,
+                        -(-numerator).div_euclid(denominator) as i64,
+                    )
+                };
+                let ((from_floor, from_ceil), (to_floor, to_ceil)) = (height(from), height(to));
+                (min(from_floor, to_floor), max(from_ceil, to_ceil))
+            };
+            let (min_row, max_row) = (
+                y_low.div_euclid(self.cell_size),
+                y_high.div_euclid(self.cell_size),
+            );
+            cells.extend((min_row..=max_row).map(|row| (column, row)));
+        }
+        cells
+    }
+}
@@ -1,110 +1,295 @@
-use common::itertools::Itertools;
+use crate::geometry::{BoundingBox, Line, Point, MAX_COORDINATE};
+use crate::heatmap::Heatmap;
+use crate::index::SpatialIndex;
+use crate::raster::{count_overlap_points, Rasterization};
 use common::lazy_static::lazy_static;
 use common::regex::Regex;
 
-static INPUT: &str = include_str!("input");
+mod geometry;
+mod heatmap;
+mod index;
+mod raster;
+mod sweep;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-struct Point {
-    x: usize,
-    y: usize,
-}
+static INPUT: &str = include_str!("input");
 
-#[derive(Debug)]
-struct Line {
-    x1: usize,
-    y1: usize,
-    x2: usize,
-    y2: usize,
+/// Command line options: `[RASTERIZATION] [--ascii] [--heatmap FILE.pgm|FILE.ppm]
+/// [--crop X1,Y1,X2,Y2] [--through X,Y] [--within X1,Y1,X2,Y2] [--min-overlap K] [--densest]`.
+#[derive(Debug, PartialEq)]
+struct Options {
+    rasterization: Rasterization,
+    ascii: bool,
+    heatmap_file: Option<String>,
+    crop: Option<BoundingBox>,
+    through: Option<Point>,
+    within: Option<BoundingBox>,
+    min_overlap: Option<usize>,
+    densest: bool,
 }
 
 fn main() -> Result<(), String> {
     let vent_lines = parse_input(INPUT)?;
-    println!(
-        "Part 1 result: {}",
-        count_overlap_points(&vent_lines, horiz_and_vert_points)
-    );
-    println!(
-        "Part 2 result: {}",
-        count_overlap_points(&vent_lines, horiz_vert_and_45_points)
-    );
-    Ok(())
-}
+    let options = parse_args(std::env::args().skip(1))?;
+    let rasterization = options.rasterization;
 
-fn count_overlap_points(vent_lines: &[Line], f: fn(&Line) -> Vec<Point>) -> usize {
-    vent_lines
-        .iter()
-        .flat_map(f)
-        .map(|point| point)
-        .sorted()
-        .dedup_with_count()
-        .filter(|(occurrences, _)| *occurrences > 1)
-        .count()
-}
+    let horiz_and_vert_lines = vent_lines.iter().filter(|line| line.is_horiz_or_vert());
+    let part_1_result = count_overlap_points(horiz_and_vert_lines, Rasterization::Diagonal45)?;
+    println!("Part 1 result: {}", part_1_result);
+
+    let part_2_result = count_overlap_points(&vent_lines, rasterization)?;
+    println!("Part 2 result: {}", part_2_result);
+
+    if options.ascii || options.heatmap_file.is_some() {
+        let to_image: Option<fn(&Heatmap) -> Vec<u8>> = match &options.heatmap_file {
+            Some(file) if file.ends_with(".pgm") => Some(Heatmap::to_pgm),
+            Some(file) if file.ends_with(".ppm") => Some(Heatmap::to_ppm),
+            Some(file) => {
+                return Err(format!(
+                    "Unknown image format {}, expected .pgm or .ppm",
+                    file
+                ))
+            }
+            None => None,
+        };
+        let heatmap = Heatmap::new(&vent_lines, rasterization, options.crop)?;
+        if options.ascii {
+            print!("{}", heatmap.to_ascii());
+        }
+        if let (Some(file), Some(to_image)) = (options.heatmap_file, to_image) {
+            std::fs::write(&file, to_image(&heatmap))
+                .map_err(|err| format!("Cannot write {}: {}", file, err))?;
+        }
+    }
 
-fn horiz_and_vert_points(line: &Line) -> Vec<Point> {
-    if line.x1 == line.x2 {
-        range(line.y1, line.y2)
-            .map(|y| Point { x: line.x1, y })
-            .collect::<Vec<_>>()
-    } else if line.y1 == line.y2 {
-        range(line.x1, line.x2)
-            .map(|x| Point { x, y: line.y1 })
-            .collect::<Vec<_>>()
-    } else {
-        vec![]
+    let has_query = options.through.is_some()
+        || options.within.is_some()
+        || options.min_overlap.is_some()
+        || options.densest;
+    if !has_query {
+        return Ok(());
     }
+    let index = SpatialIndex::new(&vent_lines);
+    if let Some(point) = options.through {
+        println!(
+            "Lines through {:?}: {:?}",
+            point,
+            index.lines_through(point)
+        );
+    }
+    if let Some(region) = options.within {
+        println!(
+            "Lines within {:?}: {:?}",
+            region,
+            index.lines_intersecting(&region)
+        );
+    }
+    if let Some(min_overlap) = options.min_overlap {
+        let points = index.points_with_overlap(min_overlap);
+        println!(
+            "{} points with {} lines or more: {:?}",
+            points.len(),
+            min_overlap,
+            points
+        );
+    }
+    if options.densest {
+        println!("Densest point: {:?}", index.densest_point());
+    }
+
+    Ok(())
 }
 
-fn horiz_vert_and_45_points(line: &Line) -> Vec<Point> {
-    if line.x1 == line.x2 {
-        range(line.y1, line.y2)
-            .map(|y| Point { x: line.x1, y })
-            .collect::<Vec<_>>()
-    } else if line.y1 == line.y2 {
-        range(line.x1, line.x2)
-            .map(|x| Point { x, y: line.y1 })
-            .collect::<Vec<_>>()
-    } else {
-        range(line.x1, line.x2)
-            .zip(range(line.y1, line.y2))
-            .map(|(x, y)| Point { x, y })
-            .collect::<Vec<_>>()
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options {
+        rasterization: Rasterization::Diagonal45,
+        ascii: false,
+        heatmap_file: None,
+        crop: None,
+        through: None,
+        within: None,
+        min_overlap: None,
+        densest: false,
+    };
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for {}", arg));
+        match arg.as_str() {
+            "--ascii" => options.ascii = true,
+            "--heatmap" => options.heatmap_file = Some(value()?),
+            "--crop" => options.crop = Some(parse_region(&value()?)?),
+            "--through" => {
+                let coordinates = parse_coordinates(&value()?, 2)?;
+                options.through = Some(Point { x: coordinates[0], y: coordinates[1] });
+            }
+            "--within" => options.within = Some(parse_region(&value()?)?),
+            "--min-overlap" => {
+                let value = value()?;
+                let min_overlap = value
+                    .parse::<usize>()
+                    .map_err(|err| format!("Invalid overlap {}: {}", value, err))?;
+                options.min_overlap = Some(min_overlap);
+            }
+            "--densest" => options.densest = true,
+            _ => options.rasterization = arg.parse::<Rasterization>()?,
+        }
     }
+    Ok(options)
+}
+
+fn parse_region(s: &str) -> Result<BoundingBox, String> {
+    let corners = parse_coordinates(s, 4)?;
+    Ok(BoundingBox::new(
+        Point { x: corners[0], y: corners[1] },
+        Point { x: corners[2], y: corners[3] },
+    ))
 }
 
-fn range(v1: usize, v2: usize) -> Box<dyn Iterator<Item = usize>> {
-    if v1 < v2 {
-        Box::new(v1..=v2)
-    } else {
-        Box::new((v2..=v1).rev())
+fn parse_coordinates(s: &str, count: usize) -> Result<Vec<i64>, String> {
+    let coordinates = s
+        .split(',')
+        .map(|v| v.parse::<i64>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("Invalid coordinates {}: {}", s, err))?;
+    if coordinates.len() != count {
+        return Err(format!("Expected {} coordinates, found {}", count, s));
+    }
+    if coordinates
+        .iter()
+        .any(|coordinate| coordinate.abs() > MAX_COORDINATE)
+    {
+        return Err(format!(
+            "Coordinates {} out of range: at most {} in absolute value",
+            s, MAX_COORDINATE
+        ));
     }
+    Ok(coordinates)
 }
 
 fn parse_input(input: &str) -> Result<Vec<Line>, String> {
-    lazy_static! {
-        static ref REGEX: Regex = Regex::new(r"(\d+),(\d+) -> (\d+),(\d+)").unwrap();
-    }
     input
         .lines()
-        .map(|line| {
-            REGEX
-                .captures(line)
-                .and_then(|cap| {
-                    let x1 = cap.get(1)?.as_str().parse::<usize>().ok()?;
-                    let y1 = cap.get(2)?.as_str().parse::<usize>().ok()?;
-                    let x2 = cap.get(3)?.as_str().parse::<usize>().ok()?;
-                    let y2 = cap.get(4)?.as_str().parse::<usize>().ok()?;
-                    Some(Line { x1, y1, x2, y2 })
-                })
-                .ok_or(format!("Failed to parse line {}", line))
-        })
+        .enumerate()
+        .map(|(index, line)| parse_line(line).map_err(|err| format!("Line {}: {}", index + 1, err)))
         .collect()
 }
 
+fn parse_line(line: &str) -> Result<Line, String> {
+    lazy_static! {
+        static ref REGEX: Regex = Regex::new(r"^(-?\d+),(-?\d+) -> (-?\d+),(-?\d+)$").unwrap();
+    }
+    let cap = REGEX
+        .captures(line)
+        .ok_or(format!("expected \"x1,y1 -> x2,y2\", found \"{}\"", line))?;
+    let coordinates = (1..=4)
+        .map(|i| {
+            let m = cap.get(i).ok_or(format!("missing coordinate {}", i))?;
+            let coordinate = m.as_str().parse::<i64>().map_err(|err| {
+                format!(
+                    "invalid coordinate {} at column {}: {}",
+                    m.as_str(),
+                    m.start() + 1,
+                    err
+                )
+            })?;
+            if coordinate.abs() > MAX_COORDINATE {
+                return Err(format!(
+                    "coordinate {} out of range at column {}: at most {} in absolute value",
+                    coordinate,
+                    m.start() + 1,
+                    MAX_COORDINATE
+                ));
+            }
+            Ok(coordinate)
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    Ok(Line {
+        x1: coordinates[0],
+        y1: coordinates[1],
+        x2: coordinates[2],
+        y2: coordinates[3],
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::geometry::{orientation, Orientation};
+    use crate::raster::{line_points, line_points_within};
+    use common::itertools::Itertools;
+
+    /// Reference implementation of [`count_overlap_points`], which enumerates every point of every
+    /// line.
+    fn count_overlap_points_naive(vent_lines: &[Line], f: impl Fn(&Line) -> Vec<Point>) -> usize {
+        vent_lines
+            .iter()
+            .flat_map(f)
+            .sorted()
+            .dedup_with_count()
+            .filter(|(occurrences, _)| *occurrences > 1)
+            .count()
+    }
+
+    fn horiz_and_vert_points(line: &Line) -> Vec<Point> {
+        if line.x1 == line.x2 {
+            range(line.y1, line.y2)
+                .map(|y| Point { x: line.x1, y })
+                .collect::<Vec<_>>()
+        } else if line.y1 == line.y2 {
+            range(line.x1, line.x2)
+                .map(|x| Point { x, y: line.y1 })
+                .collect::<Vec<_>>()
+        } else {
+            vec![]
+        }
+    }
+
+    fn horiz_vert_and_45_points(line: &Line) -> Vec<Point> {
+        if line.x1 == line.x2 {
+            range(line.y1, line.y2)
+                .map(|y| Point { x: line.x1, y })
+                .collect::<Vec<_>>()
+        } else if line.y1 == line.y2 {
+            range(line.x1, line.x2)
+                .map(|x| Point { x, y: line.y1 })
+                .collect::<Vec<_>>()
+        } else {
+            range(line.x1, line.x2)
+                .zip(range(line.y1, line.y2))
+                .map(|(x, y)| Point { x, y })
+                .collect::<Vec<_>>()
+        }
+    }
+
+    fn range(v1: i64, v2: i64) -> Box<dyn Iterator<Item = i64>> {
+        if v1 < v2 {
+            Box::new(v1..=v2)
+        } else {
+            Box::new((v2..=v1).rev())
+        }
+    }
+
+    /// Reference implementation of Bresenham's rasterization, which steps from the start of the
+    /// line.
+    fn bresenham_points_naive(line: &Line) -> Vec<Point> {
+        let (dx, dy) = line.deltas();
+        let (step_x, step_y) = (dx.signum(), dy.signum());
+        let (dx, dy) = (dx.abs(), -dy.abs());
+        let mut point = line.start();
+        let mut points = vec![point];
+        let mut error = dx + dy;
+        while point != line.end() {
+            let double_error = 2 * error;
+            if double_error >= dy {
+                error += dy;
+                point.x += step_x;
+            }
+            if double_error <= dx {
+                error += dx;
+                point.y += step_y;
+            }
+            points.push(point);
+        }
+        points
+    }
 
     static TEST_LINES: [Line; 10] = [
         Line { x1: 0, y1: 9, x2: 5, y2: 9 },
@@ -119,17 +304,356 @@ mod tests {
         Line { x1: 5, y1: 5, x2: 8, y2: 2 },
     ];
 
+    /// Deterministic pseudo-random lines in a small grid around the origin, so that they overlap a
+    /// lot. Unless `any_slope` is set, the lines are all horizontal, vertical or 45° diagonal.
+    fn pseudo_random_lines(count: usize, seed: u64, any_slope: bool) -> Vec<Line> {
+        let mut random = common::random_numbers(seed);
+        let mut next = |bound| random(bound) as i64;
+        (0..count)
+            .map(|_| {
+                let (x1, y1, length) = (next(20) - 10, next(20) - 10, next(12));
+                match next(if any_slope { 6 } else { 4 }) {
+                    0 => Line { x1, y1, x2: x1 + length, y2: y1 },
+                    1 => Line { x1, y1, x2: x1, y2: y1 + length },
+                    2 => Line { x1, y1, x2: x1 + length, y2: y1 + length },
+                    3 => Line { x1: x1 + length, y1, x2: x1, y2: y1 + length },
+                    _ => Line { x1, y1, x2: next(20) - 10, y2: next(20) - 10 },
+                }
+            })
+            .collect()
+    }
+
     #[test]
     fn test_part_1() {
-        let actual = count_overlap_points(&TEST_LINES, horiz_and_vert_points);
+        let horiz_and_vert_lines = TEST_LINES.iter().filter(|line| line.is_horiz_or_vert());
+        let actual = count_overlap_points(horiz_and_vert_lines, Rasterization::Diagonal45);
         let expected = 5;
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
+        assert_eq!(
+            expected,
+            count_overlap_points_naive(&TEST_LINES, horiz_and_vert_points)
+        );
     }
 
     #[test]
     fn test_part_2() {
-        let actual = count_overlap_points(&TEST_LINES, horiz_vert_and_45_points);
+        let actual = count_overlap_points(&TEST_LINES, Rasterization::Diagonal45);
         let expected = 12;
-        assert_eq!(expected, actual);
+        assert_eq!(Ok(expected), actual);
+        assert_eq!(
+            expected,
+            count_overlap_points_naive(&TEST_LINES, horiz_vert_and_45_points)
+        );
+    }
+
+    #[test]
+    fn test_matches_naive_implementation() {
+        for seed in 0..50 {
+            let lines = pseudo_random_lines(30, seed, false);
+            let horiz_and_vert_lines = lines.iter().filter(|line| line.is_horiz_or_vert());
+            assert_eq!(
+                Ok(count_overlap_points_naive(&lines, horiz_and_vert_points)),
+                count_overlap_points(horiz_and_vert_lines, Rasterization::Diagonal45),
+                "part 1, seed {}",
+                seed
+            );
+            assert_eq!(
+                Ok(count_overlap_points_naive(&lines, horiz_vert_and_45_points)),
+                count_overlap_points(&lines, Rasterization::Diagonal45),
+                "part 2, seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn test_any_slope_matches_naive_implementation() {
+        for seed in 0..50 {
+            let lines = pseudo_random_lines(30, seed, true);
+            let lattice_points = |line: &Line| line_points(line, Rasterization::Lattice).unwrap();
+            assert_eq!(
+                Ok(count_overlap_points_naive(&lines, lattice_points)),
+                count_overlap_points(&lines, Rasterization::Lattice),
+                "seed {}",
+                seed
+            );
+        }
+    }
+
+    #[test]
+    fn test_line_points() {
+        let line = Line { x1: 6, y1: 0, x2: 0, y2: 4 };
+        assert_eq!(
+            Ok(vec![
+                Point { x: 6, y: 0 },
+                Point { x: 3, y: 2 },
+                Point { x: 0, y: 4 }
+            ]),
+            line_points(&line, Rasterization::Lattice)
+        );
+        assert_eq!(
+            Ok(vec![
+                Point { x: 6, y: 0 },
+                Point { x: 5, y: 1 },
+                Point { x: 4, y: 1 },
+                Point { x: 3, y: 2 },
+                Point { x: 2, y: 3 },
+                Point { x: 1, y: 3 },
+                Point { x: 0, y: 4 },
+            ]),
+            line_points(&line, Rasterization::Bresenham)
+        );
+        assert!(line_points(&line, Rasterization::Diagonal45).is_err());
+
+        let region = BoundingBox::new(Point { x: -4, y: -6 }, Point { x: 5, y: 3 });
+        for seed in 0..20 {
+            for line in pseudo_random_lines(30, seed, true) {
+                let expected = bresenham_points_naive(&line);
+                assert_eq!(
+                    Ok(expected.clone()),
+                    line_points(&line, Rasterization::Bresenham)
+                );
+                let within = line_points_within(&line, Rasterization::Bresenham, &region);
+                let expected = expected
+                    .into_iter()
+                    .filter(|p| region.contains(*p))
+                    .collect();
+                assert_eq!(Ok(expected), within, "{:?}", line);
+            }
+        }
+        assert!(count_overlap_points(&[line], Rasterization::Diagonal45).is_err());
+    }
+
+    #[test]
+    fn test_huge_coordinates() {
+        let lines = [
+            Line { x1: 0, y1: 5_000_000, x2: 9_000_000, y2: 5_000_000 },
+            Line { x1: 3_000_000, y1: 5_000_000, x2: 12_000_000, y2: 5_000_000 },
+            Line { x1: 0, y1: 0, x2: 10_000_000, y2: 10_000_000 },
+            Line { x1: 7_000_000, y1: 0, x2: 7_000_000, y2: 10_000_000 },
+        ];
+        // 6_000_001 collinear points, plus the crossing at (7M, 7M). The crossings at (5M, 5M) and
+        // (7M, 5M) are part of the collinear overlap.
+        assert_eq!(
+            Ok(6_000_002),
+            count_overlap_points(&lines, Rasterization::Diagonal45)
+        );
+    }
+
+    #[test]
+    fn test_parse_input() {
+        assert_eq!(
+            Ok(vec![
+                Line { x1: 0, y1: 9, x2: 5, y2: 9 },
+                Line { x1: -8, y1: 0, x2: 0, y2: -8 },
+            ]),
+            parse_input("0,9 -> 5,9\n-8,0 -> 0,-8")
+        );
+        assert_eq!(
+            Err("Line 2: expected \"x1,y1 -> x2,y2\", found \"8,0 -> 0\"".to_owned()),
+            parse_input("0,9 -> 5,9\n8,0 -> 0\n1,1 -> 2,2")
+        );
+        assert!(parse_input("0,9 -> 5,99999999999999999999")
+            .unwrap_err()
+            .starts_with("Line 1: invalid coordinate 99999999999999999999 at column 10"));
+        // Coordinates fitting in an i64 could still overflow when computing along the lines.
+        assert_eq!(
+            Err(
+                "Line 1: coordinate -5000000000000000000 out of range at column 1: at most \
+                 1000000000 in absolute value"
+                    .to_owned()
+            ),
+            parse_input("-5000000000000000000,0 -> 5000000000000000000,0")
+        );
+        let extreme = "-1000000000,1000000000 -> 1000000000,-1000000000\n\
+                       -1000000000,-1000000000 -> 1000000000,1000000000";
+        let lines = parse_input(extreme).unwrap();
+        assert_eq!(Ok(1), count_overlap_points(&lines, Rasterization::Lattice));
+        let index = SpatialIndex::new(&lines);
+        assert_eq!(2, index.lines_through(Point { x: 0, y: 0 }).len());
+        assert!(parse_input("-1000000001,0 -> 0,0").is_err());
+        assert!(parse_region("0,0,1,1000000001").is_err());
+    }
+
+    #[test]
+    fn test_geometry() {
+        let origin = Point { x: 0, y: 0 };
+        let right = Point { x: 4, y: 0 };
+        assert_eq!(
+            Orientation::CounterClockwise,
+            orientation(origin, right, Point { x: 2, y: 3 })
+        );
+        assert_eq!(
+            Orientation::Clockwise,
+            orientation(origin, right, Point { x: 2, y: -3 })
+        );
+        assert_eq!(
+            Orientation::Collinear,
+            orientation(origin, right, Point { x: 8, y: 0 })
+        );
+
+        let line = Line { x1: -2, y1: -2, x2: 4, y2: 1 };
+        assert!(line.contains(Point { x: 2, y: 0 }));
+        assert!(!line.contains(Point { x: 6, y: 2 }));
+        assert!(line.intersects(&Line { x1: 0, y1: 0, x2: 0, y2: -5 }));
+        assert!(line.intersects(&Line { x1: 4, y1: 1, x2: 9, y2: 9 }));
+        assert!(!line.intersects(&Line { x1: 6, y1: 2, x2: 8, y2: 3 }));
+        assert!(line.intersects(&Line { x1: 0, y1: 0, x2: 5, y2: 0 }));
+    }
+
+    #[test]
+    fn test_heatmap() -> Result<(), String> {
+        let heatmap = Heatmap::new(&TEST_LINES, Rasterization::Diagonal45, None)?;
+        let expected = "\
+1.1....11.
+.111...2..
+..2.1.111.
+...1.2.2..
+.112313211
+...1.2....
+..1...1...
+.1.....1..
+1.......1.
+222111....
+";
+        assert_eq!(expected, heatmap.to_ascii());
+
+        let pgm = heatmap.to_pgm();
+        let header = b"P5\n10 10\n255\n";
+        assert_eq!(header, &pgm[..header.len()]);
+        assert_eq!(header.len() + 100, pgm.len());
+        assert_eq!([85, 0, 85], pgm[header.len()..header.len() + 3]);
+
+        let ppm = heatmap.to_ppm();
+        assert_eq!(b"P6\n10 10\n255\n".len() + 300, ppm.len());
+
+        let region = BoundingBox::new(Point { x: 3, y: 5 }, Point { x: -1, y: 3 });
+        let cropped = Heatmap::new(&TEST_LINES, Rasterization::Diagonal45, Some(region))?;
+        assert_eq!("....1\n..112\n....1\n", cropped.to_ascii());
+
+        // Only the points in the region are rasterized, and huge regions are rejected.
+        let far_lines = [
+            Line { x1: -1_000_000, y1: -1_000_000, x2: 1_000_000, y2: 1_000_000 },
+            Line { x1: 0, y1: 2, x2: 3_000_000, y2: 2 },
+        ];
+        let region = BoundingBox::new(Point { x: 0, y: 0 }, Point { x: 3, y: 2 });
+        for rasterization in [Rasterization::Diagonal45, Rasterization::Bresenham] {
+            let cropped = Heatmap::new(&far_lines, rasterization, Some(region))?;
+            assert_eq!("1...\n.1..\n1121\n", cropped.to_ascii());
+        }
+        assert!(Heatmap::new(&far_lines, Rasterization::Diagonal45, None).is_err());
+        let longest = [Line {
+            x1: -MAX_COORDINATE,
+            y1: -MAX_COORDINATE,
+            x2: MAX_COORDINATE,
+            y2: MAX_COORDINATE - 1,
+        }];
+        let region = BoundingBox::new(Point { x: 0, y: -2 }, Point { x: 3, y: 1 });
+        let cropped = Heatmap::new(&longest, Rasterization::Bresenham, Some(region))?;
+        assert_eq!("....\n....\n11..\n..1.\n", cropped.to_ascii());
+        let everything = BoundingBox::new(
+            Point { x: i64::MIN, y: i64::MIN },
+            Point { x: i64::MAX, y: i64::MAX },
+        );
+        assert!(Heatmap::new(&far_lines, Rasterization::Lattice, Some(everything)).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = [
+            "lattice",
+            "--ascii",
+            "--crop",
+            "0,5,3,-2",
+            "--heatmap",
+            "vents.ppm",
+            "--through",
+            "4,-4",
+            "--min-overlap",
+            "3",
+        ];
+        assert_eq!(
+            Ok(Options {
+                rasterization: Rasterization::Lattice,
+                ascii: true,
+                heatmap_file: Some("vents.ppm".to_owned()),
+                crop: Some(BoundingBox::new(
+                    Point { x: 0, y: -2 },
+                    Point { x: 3, y: 5 }
+                )),
+                through: Some(Point { x: 4, y: -4 }),
+                within: None,
+                min_overlap: Some(3),
+                densest: false,
+            }),
+            parse_args(args.iter().map(|arg| arg.to_string()))
+        );
+        assert!(parse_args(["--crop", "1,2,3"].iter().map(|arg| arg.to_string())).is_err());
+    }
+
+    #[test]
+    fn test_spatial_index() {
+        for cell_size in [1, 3, 100] {
+            let index = SpatialIndex::with_cell_size(&TEST_LINES, cell_size);
+
+            let through = index.lines_through(Point { x: 7, y: 4 });
+            assert_eq!(
+                vec![&TEST_LINES[2], &TEST_LINES[4]],
+                through,
+                "cell size {}",
+                cell_size
+            );
+
+            let region = BoundingBox::new(Point { x: 0, y: 0 }, Point { x: 2, y: 2 });
+            assert_eq!(
+                vec![&TEST_LINES[3], &TEST_LINES[5], &TEST_LINES[8]],
+                index.lines_intersecting(&region),
+                "cell size {}",
+                cell_size
+            );
+            // Far more cells than lines: only the occupied cells are gone through.
+            let everything = BoundingBox::new(
+                Point { x: -MAX_COORDINATE, y: -MAX_COORDINATE },
+                Point { x: MAX_COORDINATE, y: MAX_COORDINATE },
+            );
+            assert_eq!(
+                TEST_LINES.iter().collect::<Vec<_>>(),
+                index.lines_intersecting(&everything),
+                "cell size {}",
+                cell_size
+            );
+
+            let overlaps = index.points_with_overlap(2);
+            assert_eq!(12, overlaps.len(), "cell size {}", cell_size);
+            assert!(overlaps.contains(&Point { x: 7, y: 4 }));
+            assert_eq!(
+                vec![Point { x: 4, y: 4 }, Point { x: 6, y: 4 }],
+                index.points_with_overlap(3),
+                "cell size {}",
+                cell_size
+            );
+
+            assert_eq!(
+                Some((Point { x: 4, y: 4 }, 3)),
+                index.densest_point(),
+                "cell size {}",
+                cell_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_spatial_index_matches_sweep() {
+        for seed in 0..20 {
+            let lines = pseudo_random_lines(30, seed, true);
+            let index = SpatialIndex::new(&lines);
+            assert_eq!(
+                Ok(index.points_with_overlap(2).len()),
+                count_overlap_points(&lines, Rasterization::Lattice),
+                "seed {}",
+                seed
+            );
+        }
     }
 }
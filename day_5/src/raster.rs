@@ -0,0 +1,128 @@
+use crate::geometry::{step_range, BoundingBox, Line, Point};
+use crate::sweep;
+use common::itertools::Itertools;
+use std::cmp::{max, min};
+use std::str::FromStr;
+
+/// How a vent line is turned into the grid points it covers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rasterization {
+    /// Only horizontal, vertical and 45° diagonal lines, as described by the puzzle. Any other
+    /// line is rejected.
+    Diagonal45,
+    /// Every grid point lying exactly on the line, found by stepping along the slope reduced by
+    /// the gcd of its components.
+    Lattice,
+    /// One point per step along the major axis, the closest to the ideal line (Bresenham's
+    /// algorithm).
+    Bresenham,
+}
+
+impl FromStr for Rasterization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "diagonal45" => Ok(Rasterization::Diagonal45),
+            "lattice" => Ok(Rasterization::Lattice),
+            "bresenham" => Ok(Rasterization::Bresenham),
+            _ => Err(format!("Invalid rasterization {}", s)),
+        }
+    }
+}
+
+/// Counts the points covered by at least two of the given lines.
+pub fn count_overlap_points<'a>(
+    vent_lines: impl IntoIterator<Item = &'a Line>,
+    rasterization: Rasterization,
+) -> Result<usize, String> {
+    let vent_lines = vent_lines.into_iter().collect::<Vec<_>>();
+    match rasterization {
+        Rasterization::Diagonal45 => {
+            if let Some(line) = vent_lines.iter().find(|line| !line.is_horiz_vert_or_45()) {
+                return Err(format!(
+                    "Line {:?} is not horizontal, vertical or 45°",
+                    line
+                ));
+            }
+            Ok(sweep::count_overlap_points(vent_lines))
+        }
+        Rasterization::Lattice => Ok(sweep::count_overlap_points(vent_lines)),
+        Rasterization::Bresenham => {
+            let points = vent_lines
+                .into_iter()
+                .map(|line| line_points(line, rasterization))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(points
+                .into_iter()
+                .flatten()
+                .sorted()
+                .dedup_with_count()
+                .filter(|(occurrences, _)| *occurrences > 1)
+                .count())
+        }
+    }
+}
+
+/// All the points covered by a line, from `(x1, y1)` to `(x2, y2)`.
+pub fn line_points(line: &Line, rasterization: Rasterization) -> Result<Vec<Point>, String> {
+    line_points_within(line, rasterization, &line.bounding_box())
+}
+
+/// Points covered by a line which lie inside `region`, without going through the others.
+pub fn line_points_within(
+    line: &Line,
+    rasterization: Rasterization,
+    region: &BoundingBox,
+) -> Result<Vec<Point>, String> {
+    match rasterization {
+        Rasterization::Diagonal45 if !line.is_horiz_vert_or_45() => Err(format!(
+            "Line {:?} is not horizontal, vertical or 45°",
+            line
+        )),
+        Rasterization::Diagonal45 | Rasterization::Lattice => {
+            Ok(line.lattice_points_within(region))
+        }
+        Rasterization::Bresenham => Ok(bresenham_points_within(line, region)),
+    }
+}
+
+/// Points of Bresenham's line which lie inside `region`, computed directly at each step along
+/// the major axis within the region rather than by stepping from the start of the line.
+fn bresenham_points_within(line: &Line, region: &BoundingBox) -> Vec<Point> {
+    let (dx, dy) = line.deltas();
+    let x_major = dx.abs() >= dy.abs();
+    let (major, minor) = if x_major {
+        (dx.abs(), dy.abs())
+    } else {
+        (dy.abs(), dx.abs())
+    };
+    let (first, last) = if x_major {
+        step_range(line.x1, dx.signum(), region.min.x, region.max.x)
+    } else {
+        step_range(line.y1, dy.signum(), region.min.y, region.max.y)
+    };
+    (max(0, first)..=min(major, last))
+        .map(|step| {
+            // After `step` steps along the major axis, the minor axis has moved by
+            // `step * minor / major`, rounded half up.
+            let moved = if major == 0 {
+                0
+            } else {
+                ((2 * step as i128 * minor as i128 + major as i128) / (2 * major as i128)) as i64
+            };
+            if x_major {
+                Point {
+                    x: line.x1 + step * dx.signum(),
+                    y: line.y1 + moved * dy.signum(),
+                }
+            } else {
+                Point {
+                    x: line.x1 + moved * dx.signum(),
+                    y: line.y1 + step * dy.signum(),
+                }
+            }
+        })
+        .filter(|point| region.contains(*point))
+        .collect()
+}
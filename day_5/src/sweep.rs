@@ -0,0 +1,180 @@
+use crate::geometry::{gcd, Line, Point};
+use std::cmp::min;
+use std::collections::{HashMap, HashSet};
+
+/// Counts the lattice points covered by at least two of the given lines, without enumerating the
+/// points of each line: collinear lines are merged as intervals along their common carrier, and
+/// crossing lines are found with a sweep over the x axis.
+pub fn count_overlap_points<'a>(vent_lines: impl IntoIterator<Item = &'a Line>) -> usize {
+    let mut segments = vent_lines.into_iter().map(Segment::new).collect::<Vec<_>>();
+
+    let mut intervals_by_carrier: HashMap<Carrier, Vec<(i64, i64)>> = HashMap::new();
+    for segment in &segments {
+        intervals_by_carrier
+            .entry(segment.carrier)
+            .or_default()
+            .push(segment.interval());
+    }
+    let overlaps_by_carrier = intervals_by_carrier
+        .into_iter()
+        .map(|(carrier, intervals)| (carrier, multi_covered_intervals(intervals)))
+        .collect::<HashMap<_, _>>();
+
+    let collinear_overlaps: i64 = overlaps_by_carrier
+        .values()
+        .flatten()
+        .map(|(start, end)| end - start + 1)
+        .sum();
+
+    // Carriers of the segments crossing at each crossing point.
+    let mut crossings: HashMap<Point, HashSet<Carrier>> = HashMap::new();
+    segments.sort_by_key(|segment| segment.start.x);
+    let mut active: Vec<&Segment> = Vec::new();
+    for segment in &segments {
+        active.retain(|other| other.end().x >= segment.start.x);
+        for other in &active {
+            if let Some(point) = segment.crossing(other) {
+                let carriers = crossings.entry(point).or_default();
+                carriers.insert(segment.carrier);
+                carriers.insert(other.carrier);
+            }
+        }
+        active.push(segment);
+    }
+
+    // A crossing point was already counted once for each carrier where it is part of a collinear
+    // overlap, but it must be counted exactly once overall.
+    let crossing_overlaps: i64 = crossings
+        .iter()
+        .map(|(point, carriers)| {
+            let already_counted = carriers
+                .iter()
+                .filter(|carrier| is_covered(&overlaps_by_carrier[carrier], carrier.index(*point)))
+                .count() as i64;
+            1 - already_counted
+        })
+        .sum();
+
+    (collinear_overlaps + crossing_overlaps) as usize
+}
+
+/// The infinite line supporting a segment. Its lattice points are `base + k * (dx, dy)` for every
+/// integer `k`, where `(dx, dy)` is the primitive direction of the line, oriented towards
+/// increasing x (or increasing y for vertical lines).
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+struct Carrier {
+    dx: i64,
+    dy: i64,
+    /// `dx * y - dy * x`, which is the same for every point of the line.
+    offset: i64,
+}
+
+impl Carrier {
+    /// Index `k` of a lattice point of this carrier, consistent along the whole carrier.
+    fn index(&self, point: Point) -> i64 {
+        (self.dx * point.x + self.dy * point.y).div_euclid(self.dx * self.dx + self.dy * self.dy)
+    }
+}
+
+#[derive(Debug)]
+struct Segment {
+    carrier: Carrier,
+    /// The lowest end of the segment, in (x, y) order.
+    start: Point,
+    /// Number of steps along the carrier direction from `start` to the other end.
+    steps: i64,
+}
+
+impl Segment {
+    fn new(line: &Line) -> Segment {
+        let start = min(line.start(), line.end());
+        let (dx, dy) = line.deltas();
+        let (dx, dy) = if start == line.start() {
+            (dx, dy)
+        } else {
+            (-dx, -dy)
+        };
+        let steps = gcd(dx.unsigned_abs(), dy.unsigned_abs()) as i64;
+        // A single point is treated as a horizontal segment of length 0.
+        let (dx, dy) = if steps == 0 {
+            (1, 0)
+        } else {
+            (dx / steps, dy / steps)
+        };
+        let carrier = Carrier { dx, dy, offset: dx * start.y - dy * start.x };
+        Segment { carrier, start, steps }
+    }
+
+    fn end(&self) -> Point {
+        Point {
+            x: self.start.x + self.steps * self.carrier.dx,
+            y: self.start.y + self.steps * self.carrier.dy,
+        }
+    }
+
+    fn line(&self) -> Line {
+        let end = self.end();
+        Line { x1: self.start.x, y1: self.start.y, x2: end.x, y2: end.y }
+    }
+
+    /// Closed interval of carrier indices covered by this segment.
+    fn interval(&self) -> (i64, i64) {
+        let start = self.carrier.index(self.start);
+        (start, start + self.steps)
+    }
+
+    /// The lattice point where this segment crosses a non-collinear segment, if any.
+    fn crossing(&self, other: &Segment) -> Option<Point> {
+        let (a, b) = (&self.carrier, &other.carrier);
+        let denominator = a.dx * b.dy - a.dy * b.dx;
+        if denominator == 0 || !self.line().intersects(&other.line()) {
+            return None;
+        }
+        let (ox, oy) = (other.start.x - self.start.x, other.start.y - self.start.y);
+        let self_numerator = ox * b.dy - oy * b.dx;
+        let other_numerator = ox * a.dy - oy * a.dx;
+        if self_numerator % denominator != 0 || other_numerator % denominator != 0 {
+            return None;
+        }
+        let self_step = self_numerator / denominator;
+        let other_step = other_numerator / denominator;
+        if (0..=self.steps).contains(&self_step) && (0..=other.steps).contains(&other_step) {
+            Some(Point {
+                x: self.start.x + self_step * a.dx,
+                y: self.start.y + self_step * a.dy,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Sorted, disjoint intervals covered by at least two of the given closed intervals.
+fn multi_covered_intervals(intervals: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+    let mut events = intervals
+        .into_iter()
+        .flat_map(|(start, end)| vec![(start, 1), (end + 1, -1)])
+        .collect::<Vec<(i64, i64)>>();
+    events.sort_unstable();
+
+    let mut result = Vec::new();
+    let mut coverage = 0;
+    let mut overlap_start = None;
+    for (position, delta) in events {
+        coverage += delta;
+        match overlap_start {
+            None if coverage >= 2 => overlap_start = Some(position),
+            Some(start) if coverage < 2 => {
+                result.push((start, position - 1));
+                overlap_start = None;
+            }
+            _ => {}
+        }
+    }
+    result
+}
+
+fn is_covered(intervals: &[(i64, i64)], index: i64) -> bool {
+    let after = intervals.partition_point(|(start, _)| *start <= index);
+    after > 0 && intervals[after - 1].1 >= index
+}
@@ -0,0 +1,177 @@
+use std::cmp::Ordering;
+use std::fmt;
+use std::str::FromStr;
+
+/// A number of fish, which can be added and multiplied without silently wrapping around.
+pub trait FishCount: Clone + Default + fmt::Display {
+    fn from_usize(value: usize) -> Self;
+
+    /// `None` if the result cannot be represented.
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+
+    /// `None` if the result cannot be represented.
+    fn checked_mul(&self, factor: usize) -> Option<Self>;
+
+    /// Closest floating-point value, for statistics.
+    fn to_f64(&self) -> f64;
+}
+
+impl FishCount for usize {
+    fn from_usize(value: usize) -> Self {
+        value
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        usize::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, factor: usize) -> Option<Self> {
+        usize::checked_mul(*self, factor)
+    }
+
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+}
+
+impl FishCount for u128 {
+    fn from_usize(value: usize) -> Self {
+        value as u128
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        u128::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, factor: usize) -> Option<Self> {
+        u128::checked_mul(*self, factor as u128)
+    }
+
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+}
+
+/// Arbitrary-precision unsigned integer, which never overflows.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+pub struct BigUint {
+    /// Base 2^32 digits, least significant first, without trailing zeros.
+    digits: Vec<u32>,
+}
+
+impl FishCount for BigUint {
+    fn from_usize(value: usize) -> Self {
+        let mut value = value as u128;
+        let mut digits = Vec::new();
+        while value > 0 {
+            digits.push(value as u32);
+            value >>= 32;
+        }
+        BigUint { digits }
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        let mut digits = Vec::with_capacity(self.digits.len().max(other.digits.len()) + 1);
+        let mut carry = 0;
+        for i in 0..self.digits.len().max(other.digits.len()) {
+            let sum = *self.digits.get(i).unwrap_or(&0) as u64
+                + *other.digits.get(i).unwrap_or(&0) as u64
+                + carry;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        if carry > 0 {
+            digits.push(carry as u32);
+        }
+        Some(BigUint { digits })
+    }
+
+    fn checked_mul(&self, factor: usize) -> Option<Self> {
+        if factor == 0 {
+            return Some(BigUint::default());
+        }
+        let mut digits = Vec::with_capacity(self.digits.len() + 2);
+        let mut carry = 0;
+        for digit in &self.digits {
+            let product = *digit as u128 * factor as u128 + carry;
+            digits.push(product as u32);
+            carry = product >> 32;
+        }
+        while carry > 0 {
+            digits.push(carry as u32);
+            carry >>= 32;
+        }
+        Some(BigUint { digits })
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.digits
+            .iter()
+            .rev()
+            .fold(0.0, |acc, digit| acc * 4294967296.0 + *digit as f64)
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.digits
+            .len()
+            .cmp(&other.digits.len())
+            .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for BigUint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("Empty number".to_owned());
+        }
+        s.chars().try_fold(BigUint::default(), |acc, c| {
+            let digit = c
+                .to_digit(10)
+                .ok_or(format!("{} is not a valid digit", c))?;
+            acc.checked_mul(10)
+                .and_then(|acc| acc.checked_add(&BigUint::from_usize(digit as usize)))
+                .ok_or_else(|| format!("Cannot parse {}", s))
+        })
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const CHUNK: u64 = 1_000_000_000;
+        // Base 10^9 digits, least significant first, computed by repeated division.
+        let mut chunks = Vec::new();
+        let mut remaining = self.digits.clone();
+        while !remaining.is_empty() {
+            let mut remainder = 0;
+            for digit in remaining.iter_mut().rev() {
+                let value = (remainder << 32) | *digit as u64;
+                *digit = (value / CHUNK) as u32;
+                remainder = value % CHUNK;
+            }
+            chunks.push(remainder);
+            while remaining.last() == Some(&0) {
+                remaining.pop();
+            }
+        }
+        match chunks.split_last() {
+            None => write!(f, "0"),
+            Some((most_significant, others)) => {
+                write!(f, "{}", most_significant)?;
+                for chunk in others.iter().rev() {
+                    write!(f, "{:09}", chunk)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
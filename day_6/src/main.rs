@@ -1,26 +1,151 @@
+use crate::count::{BigUint, FishCount};
+use crate::matrix::simulate_matrix;
+use crate::model::SpawnModel;
+use crate::series::{first_day_exceeding, summarize, to_csv, Population};
+
+mod count;
+mod matrix;
+mod model;
+mod series;
+
 static INPUT: &str = include_str!("input");
 
-const NEW_FISH_TIMER: usize = 8;
-const RESET_FISH_TIMER: usize = 6;
-const MAX_FISH_TIMER: usize = NEW_FISH_TIMER;
+/// Index = fish state, value = number of fish in that state (see [`SpawnModel::state`]).
+/// With the lanternfish model, the state is simply the timer: e.g. if `fish[2] == 5`, there are 5
+/// fish with a timer of 2.
+type Fish<T = usize> = Vec<T>;
+
+/// Number of days covered by the time series when `DAYS` is not specified.
+const DEFAULT_SERIES_DAYS: usize = 256;
 
-/// Index = fish timer, value = number of fish with that timer.
-/// E.g. if `fish[2] == 5`, there are 5 fish with a timer of 2.
-type Fish = [usize; MAX_FISH_TIMER + 1];
+/// Command line arguments: `[DAYS [MODULUS]] [--new-timer N] [--reset-timer N] [--offspring N]
+/// [--lifespan N] [--csv FILE] [--threshold N]`.
+struct Options {
+    model: SpawnModel,
+    days: Option<u64>,
+    modulus: Option<usize>,
+    csv_file: Option<String>,
+    threshold: Option<BigUint>,
+}
 
 fn main() -> Result<(), String> {
-    let initial_fish = parse_input(INPUT)?;
+    let options = parse_args(std::env::args().skip(1))?;
+    let model = &options.model;
+    let initial_fish = parse_input(INPUT, model)?;
+
+    let part_1_end_state = simulate(model, &initial_fish, 80)?;
+    println!("Part 1 result: {}", count_fish(&part_1_end_state)?);
+
+    let part_2_end_state = simulate(model, &initial_fish, 256)?;
+    println!("Part 2 result: {}", count_fish(&part_2_end_state)?);
 
-    let part_1_end_state = simulate(&initial_fish, 80);
-    println!("Part 1 result: {}", count_fish(&part_1_end_state));
+    // Simulates any number of days. Without a modulus, the exact result is computed with big
+    // integers, day by day.
+    if let Some(days) = options.days {
+        let count = match options.modulus {
+            Some(modulus) => simulate_matrix(model, &initial_fish, days, Some(modulus))?
+                .iter()
+                .fold(0, |acc, fish| {
+                    ((acc as u128 + *fish as u128) % modulus as u128) as usize
+                })
+                .to_string(),
+            None => {
+                let initial_fish = initial_fish
+                    .iter()
+                    .map(|fish| BigUint::from_usize(*fish))
+                    .collect::<Vec<_>>();
+                count_fish(&simulate(model, &initial_fish, days as usize)?)?.to_string()
+            }
+        };
+        println!("Result after {} days: {}", days, count);
+    }
+
+    // Time series of the exact population, day by day.
+    if options.csv_file.is_some() || options.threshold.is_some() {
+        let series_days = options
+            .days
+            .map_or(DEFAULT_SERIES_DAYS, |days| days as usize);
+        let initial_fish = initial_fish
+            .iter()
+            .map(|fish| BigUint::from_usize(*fish))
+            .collect::<Vec<_>>();
+        let histograms = Population::new(model, &initial_fish)
+            .take(series_days + 1)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(file) = &options.csv_file {
+            std::fs::write(file, to_csv(&histograms)?)
+                .map_err(|err| format!("Cannot write {}: {}", file, err))?;
+            let totals = histograms
+                .iter()
+                .map(|histogram| count_fish(histogram))
+                .collect::<Result<Vec<_>, _>>()?;
+            match summarize(&totals) {
+                Some(summary) => println!(
+                    "Daily growth rate: {:.4}%, doubling time: {}",
+                    summary.daily_growth_rate * 100.0,
+                    summary
+                        .doubling_time
+                        .map_or("never".to_owned(), |days| format!("{:.2} days", days))
+                ),
+                None => println!("Not enough data to summarize the population"),
+            }
+        }
 
-    let part_2_end_state = simulate(&initial_fish, 256);
-    println!("Part 2 result: {}", count_fish(&part_2_end_state));
+        if let Some(threshold) = &options.threshold {
+            let histograms = histograms.into_iter().map(Ok);
+            match first_day_exceeding(histograms, threshold)? {
+                Some(day) => println!("More than {} fish on day {}", threshold, day),
+                None => println!("At most {} fish until day {}", threshold, series_days),
+            }
+        }
+    }
 
     Ok(())
 }
 
-fn parse_input(input: &str) -> Result<Fish, String> {
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut model = SpawnModel::LANTERNFISH;
+    let (mut csv_file, mut threshold) = (None, None);
+    let mut positional = Vec::new();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            positional.push(arg);
+            continue;
+        }
+        let value = args.next().ok_or(format!("Missing value for {}", arg))?;
+        let number = || {
+            value
+                .parse::<usize>()
+                .map_err(|err| format!("Invalid value {} for {}: {}", value, arg, err))
+        };
+        match arg.as_str() {
+            "--new-timer" => model.new_timer = number()?,
+            "--reset-timer" => model.reset_timer = number()?,
+            "--offspring" => model.offspring = number()?,
+            "--lifespan" => model.lifespan = Some(number()?),
+            "--csv" => csv_file = Some(value),
+            "--threshold" => threshold = Some(value.parse::<BigUint>()?),
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    let days = positional
+        .first()
+        .map(|days| days.parse::<u64>().map_err(|err| err.to_string()))
+        .transpose()?;
+    let modulus = positional
+        .get(1)
+        .map(|modulus| match modulus.parse::<usize>() {
+            Ok(0) => Err("Modulus must be positive".to_owned()),
+            Ok(modulus) => Ok(modulus),
+            Err(err) => Err(err.to_string()),
+        })
+        .transpose()?;
+    model.validate()?;
+    Ok(Options { model, days, modulus, csv_file, threshold })
+}
+
+fn parse_input(input: &str, model: &SpawnModel) -> Result<Fish, String> {
     let fish_vec = input
         .lines()
         .next()
@@ -31,56 +156,222 @@ fn parse_input(input: &str) -> Result<Fish, String> {
         .map_err(|err| err.to_string())?;
 
     let max_value = fish_vec.iter().max().ok_or("Empty initial fish")?;
-    if *max_value > MAX_FISH_TIMER {
+    if *max_value > model.max_timer() {
         return Err(format!("Illegal fish value {}", max_value));
     }
 
-    let mut all_fish: Fish = [0; MAX_FISH_TIMER + 1];
+    let mut all_fish: Fish = vec![0; model.states()];
     for fish in fish_vec {
-        all_fish[fish] += 1;
+        all_fish[model.state(fish, 0)] += 1;
     }
     Ok(all_fish)
 }
 
-fn simulate(initial_fish: &Fish, days: usize) -> Fish {
-    let mut all_fish = initial_fish.clone();
+/// Fails instead of overflowing if the number of fish in some state cannot be represented by
+/// `T`.
+fn simulate<T: FishCount>(
+    model: &SpawnModel,
+    initial_fish: &[T],
+    days: usize,
+) -> Result<Fish<T>, String> {
+    let mut all_fish = initial_fish.to_vec();
 
-    for _ in 0..days {
-        let birth_rate = all_fish[0];
-        for timer in 0..MAX_FISH_TIMER {
-            all_fish[timer] = all_fish[timer + 1];
-        }
-        all_fish[RESET_FISH_TIMER] += birth_rate;
-        all_fish[NEW_FISH_TIMER] = birth_rate;
+    for day in 1..=days {
+        all_fish = model
+            .step(&all_fish)
+            .ok_or(format!("Number of fish overflowed on day {}", day))?;
     }
 
-    all_fish
+    Ok(all_fish)
 }
 
-fn count_fish(fish: &Fish) -> usize {
-    fish.iter().sum()
+fn count_fish<T: FishCount>(fish: &[T]) -> Result<T, String> {
+    fish.iter().try_fold(T::default(), |acc, next| {
+        acc.checked_add(next)
+            .ok_or_else(|| "Total number of fish overflowed".to_owned())
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    static TEST_FISH: Fish = [0, 1, 1, 2, 1, 0, 0, 0, 0];
+    static TEST_FISH: [usize; 9] = [0, 1, 1, 2, 1, 0, 0, 0, 0];
+
+    static LANTERNFISH: SpawnModel = SpawnModel::LANTERNFISH;
 
     #[test]
     fn test() {
-        let actual_1 = simulate(&TEST_FISH, 1);
-        let expected_1: Fish = [1, 1, 2, 1, 0, 0, 0, 0, 0];
-        assert_eq!(expected_1, actual_1);
+        let actual_1 = simulate(&LANTERNFISH, &TEST_FISH, 1);
+        let expected_1: Fish = vec![1, 1, 2, 1, 0, 0, 0, 0, 0];
+        assert_eq!(Ok(expected_1), actual_1);
+
+        let actual_18 = simulate(&LANTERNFISH, &TEST_FISH, 18);
+        let expected_18: Fish = vec![3, 5, 3, 2, 2, 1, 5, 1, 4];
+        assert_eq!(Ok(expected_18), actual_18);
+
+        let actual_80 = simulate(&LANTERNFISH, &TEST_FISH, 80).unwrap();
+        assert_eq!(Ok(5934), count_fish(&actual_80));
+
+        let actual_256 = simulate(&LANTERNFISH, &TEST_FISH, 256).unwrap();
+        assert_eq!(Ok(26984457539), count_fish(&actual_256));
+    }
+
+    #[test]
+    fn test_overflow() {
+        assert_eq!(
+            Err("Number of fish overflowed on day 512".to_owned()),
+            simulate(&LANTERNFISH, &TEST_FISH, 600)
+        );
+
+        let fish_u128 = TEST_FISH.map(u128::from_usize);
+        let fish_big = TEST_FISH.map(BigUint::from_usize);
+        let actual_u128 = count_fish(&simulate(&LANTERNFISH, &fish_u128, 600).unwrap()).unwrap();
+        let actual_big = count_fish(&simulate(&LANTERNFISH, &fish_big, 600).unwrap()).unwrap();
+        assert_eq!(278946188929354604138512, actual_u128);
+        assert_eq!("278946188929354604138512", actual_big.to_string());
+
+        assert_eq!(
+            Err("Number of fish overflowed on day 1021".to_owned()),
+            simulate(&LANTERNFISH, &fish_u128, 2000)
+        );
+        assert_eq!(
+            Err("Total number of fish overflowed".to_owned()),
+            count_fish(&[u128::MAX, 1, 0, 0, 0, 0, 0, 0, 0])
+        );
+        let actual_big = count_fish(&simulate(&LANTERNFISH, &fish_big, 2000).unwrap()).unwrap();
+        assert_eq!(77, actual_big.to_string().len());
+    }
+
+    #[test]
+    fn test_simulate_matrix() {
+        let newborn = [0, 0, 0, 0, 0, 0, 0, 0, 1];
+        for initial_fish in [&TEST_FISH, &newborn] {
+            for days in 0..=600 {
+                assert_eq!(
+                    simulate(&LANTERNFISH, initial_fish, days as usize).ok(),
+                    simulate_matrix(&LANTERNFISH, initial_fish, days, None).ok(),
+                    "{} days",
+                    days
+                );
+            }
+        }
+        assert!(simulate_matrix(&LANTERNFISH, &newborn, 529, None).is_ok());
+
+        let modulus = 1_000_000_007;
+        let expected = simulate(&LANTERNFISH, &TEST_FISH, 256)
+            .unwrap()
+            .iter()
+            .map(|fish| fish % modulus)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            expected,
+            simulate_matrix(&LANTERNFISH, &TEST_FISH, 256, Some(modulus)).unwrap()
+        );
+        assert_eq!(
+            Err("Number of fish overflowed after 600 days".to_owned()),
+            simulate_matrix(&LANTERNFISH, &TEST_FISH, 600, None)
+        );
+
+        let days = 1_000_000_000_000;
+        let end_state = simulate_matrix(&LANTERNFISH, &TEST_FISH, days, Some(modulus)).unwrap();
+        let halfway = simulate_matrix(&LANTERNFISH, &TEST_FISH, days / 2, Some(modulus)).unwrap();
+        assert_eq!(
+            end_state,
+            simulate_matrix(&LANTERNFISH, &halfway, days / 2, Some(modulus)).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_spawn_model() {
+        // Each fish spawns twins once, then dies.
+        let model = SpawnModel {
+            new_timer: 2,
+            reset_timer: 1,
+            offspring: 2,
+            lifespan: Some(1),
+        };
+        let initial_fish = parse_input("0", &model).unwrap();
+        let populations = (0..=6)
+            .map(|days| count_fish(&simulate(&model, &initial_fish, days).unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![1, 2, 2, 2, 4, 4, 4], populations);
+
+        // Each fish spawns twice, then dies.
+        let model = SpawnModel {
+            new_timer: 3,
+            reset_timer: 1,
+            offspring: 1,
+            lifespan: Some(2),
+        };
+        let initial_fish = parse_input("0", &model).unwrap();
+        let populations = (0..=6)
+            .map(|days| count_fish(&simulate(&model, &initial_fish, days).unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![1, 2, 2, 2, 2, 3, 3], populations);
+
+        for days in 0..100 {
+            assert_eq!(
+                simulate(&model, &initial_fish, days as usize).unwrap(),
+                simulate_matrix(&model, &initial_fish, days, None).unwrap(),
+                "{} days",
+                days
+            );
+        }
+
+        assert!(parse_input("4", &model).is_err());
+        assert!(SpawnModel { lifespan: Some(0), ..SpawnModel::LANTERNFISH }
+            .validate()
+            .is_err());
+        let parse = |args: &[&str]| parse_args(args.iter().map(|arg| arg.to_string()));
+        assert!(parse(&["--new-timer", "18446744073709551615"]).is_err());
+        assert!(parse(&["--lifespan", "1000000000000"]).is_err());
+        assert!(parse(&["--new-timer", "99", "--lifespan", "100"]).is_ok());
+        assert!(parse(&["--new-timer", "99", "--lifespan", "101"]).is_err());
+    }
+
+    #[test]
+    fn test_population_series() {
+        let histograms = Population::new(&LANTERNFISH, &TEST_FISH)
+            .take(19)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(19, histograms.len());
+        assert_eq!(TEST_FISH.to_vec(), histograms[0]);
+        assert_eq!(vec![3, 5, 3, 2, 2, 1, 5, 1, 4], histograms[18]);
+
+        let csv = to_csv(&histograms[..2]).unwrap();
+        let expected_csv = "\
+day,total,timer_0,timer_1,timer_2,timer_3,timer_4,timer_5,timer_6,timer_7,timer_8
+0,5,0,1,1,2,1,0,0,0,0
+1,5,1,1,2,1,0,0,0,0,0
+";
+        assert_eq!(expected_csv, csv);
 
-        let actual_18 = simulate(&TEST_FISH, 18);
-        let expected_18: Fish = [3, 5, 3, 2, 2, 1, 5, 1, 4];
-        assert_eq!(expected_18, actual_18);
+        let population = Population::new(&LANTERNFISH, &TEST_FISH);
+        assert_eq!(Ok(Some(80)), first_day_exceeding(population, &5933));
+        let population = Population::new(&LANTERNFISH, &TEST_FISH).take(80);
+        assert_eq!(Ok(None), first_day_exceeding(population, &5933));
+        let last = Population::new(&LANTERNFISH, &TEST_FISH).take(600).last();
+        assert_eq!(
+            Some(Err("Number of fish overflowed on day 512".to_owned())),
+            last
+        );
 
-        let actual_80 = simulate(&TEST_FISH, 80);
-        assert_eq!(5934, count_fish(&actual_80));
+        // The population grows by a factor of about 1.09 each day in the long run.
+        let totals = Population::new(&LANTERNFISH, &TEST_FISH)
+            .take(401)
+            .map(|histogram| count_fish(&histogram.unwrap()).unwrap())
+            .collect::<Vec<_>>();
+        let summary = summarize(&totals).unwrap();
+        assert!((summary.daily_growth_rate - 0.0910).abs() < 0.001);
+        assert!((summary.doubling_time.unwrap() - 7.95).abs() < 0.1);
 
-        let actual_256 = simulate(&TEST_FISH, 256);
-        assert_eq!(26984457539, count_fish(&actual_256));
+        assert_eq!(None, summarize(&[5usize]));
+        assert_eq!(None, summarize(&[5usize, 5]).unwrap().doubling_time);
+        assert_eq!(
+            Ok(BigUint::from_usize(123456789012345)),
+            "123456789012345".parse::<BigUint>()
+        );
     }
 }
@@ -0,0 +1,95 @@
+use crate::model::SpawnModel;
+use crate::Fish;
+use std::convert::TryFrom;
+
+/// `matrix[to][from]` = number of fish in state `to` produced in one day by a fish in state
+/// `from`. Without a modulus, entries saturate at `u128::MAX`, so that an entry is exact whenever
+/// it fits.
+type Matrix = Vec<Vec<u128>>;
+
+/// Same as [`crate::simulate`], but computes the `days`-th power of the daily transition matrix by
+/// repeated squaring, in O(log(days)) matrix products.
+///
+/// If `modulus` is specified, all the numbers of fish are computed modulo `modulus`, which allows
+/// simulating any number of days without overflowing. Otherwise, fails if a number of fish in the
+/// end state does not fit in a `usize`.
+pub fn simulate_matrix(
+    model: &SpawnModel,
+    initial_fish: &[usize],
+    days: u64,
+    modulus: Option<usize>,
+) -> Result<Fish, String> {
+    let modulus = modulus.map(|modulus| modulus as u128);
+    let transitions = power(&transition_matrix(model), days, modulus);
+    transitions
+        .iter()
+        .map(|row| {
+            let fish = row.iter().zip(initial_fish).fold(0, |acc, (count, fish)| {
+                add(acc, multiply(*count, *fish as u128, modulus), modulus)
+            });
+            usize::try_from(fish)
+                .map_err(|_| format!("Number of fish overflowed after {} days", days))
+        })
+        .collect()
+}
+
+fn transition_matrix(model: &SpawnModel) -> Matrix {
+    let mut matrix = vec![vec![0; model.states()]; model.states()];
+    let transitions = (0..model.states()).map(|state| model.transitions(state));
+    for (from, transitions) in transitions.enumerate() {
+        for (to, count) in transitions {
+            matrix[to][from] += count as u128;
+        }
+    }
+    matrix
+}
+
+fn identity(size: usize) -> Matrix {
+    (0..size)
+        .map(|i| (0..size).map(|j| if i == j { 1 } else { 0 }).collect())
+        .collect()
+}
+
+fn power(matrix: &Matrix, mut exponent: u64, modulus: Option<u128>) -> Matrix {
+    let mut result = identity(matrix.len());
+    let mut base = matrix.clone();
+    while exponent > 0 {
+        if exponent % 2 == 1 {
+            result = product(&result, &base, modulus);
+        }
+        exponent /= 2;
+        if exponent > 0 {
+            base = product(&base, &base, modulus);
+        }
+    }
+    result
+}
+
+fn product(a: &Matrix, b: &Matrix, modulus: Option<u128>) -> Matrix {
+    let size = a.len();
+    let mut result = vec![vec![0; size]; size];
+    for i in 0..size {
+        for j in 0..size {
+            for k in 0..size {
+                result[i][j] = add(result[i][j], multiply(a[i][k], b[k][j], modulus), modulus);
+            }
+        }
+    }
+    result
+}
+
+/// With a modulus, both numbers must fit in a `usize`.
+fn add(a: u128, b: u128, modulus: Option<u128>) -> u128 {
+    match modulus {
+        Some(m) => (a + b) % m,
+        None => a.saturating_add(b),
+    }
+}
+
+/// With a modulus, both numbers must fit in a `usize`.
+fn multiply(a: u128, b: u128, modulus: Option<u128>) -> u128 {
+    match modulus {
+        Some(m) => a * b % m,
+        None => a.saturating_mul(b),
+    }
+}
@@ -0,0 +1,105 @@
+use crate::count::FishCount;
+
+/// Largest supported number of fish states, which keeps histograms and transition matrices small.
+const MAX_STATES: usize = 10_000;
+
+/// How a species of fish reproduces.
+///
+/// The state of a fish is its timer, and the number of times it has already spawned if it dies
+/// after a limited number of cycles.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpawnModel {
+    /// Timer of a newborn fish.
+    pub new_timer: usize,
+    /// Timer of a fish right after it spawned.
+    pub reset_timer: usize,
+    /// Number of newborns each time a fish spawns.
+    pub offspring: usize,
+    /// Number of times a fish spawns before dying, or `None` if fish never die.
+    pub lifespan: Option<usize>,
+}
+
+impl SpawnModel {
+    /// The lanternfish from the puzzle: newborns start with a timer of 8, a single newborn is
+    /// spawned when the timer goes past 0, and the timer is then reset to 6.
+    pub const LANTERNFISH: SpawnModel =
+        SpawnModel { new_timer: 8, reset_timer: 6, offspring: 1, lifespan: None };
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.lifespan == Some(0) {
+            return Err("Fish must live for at least one cycle".to_owned());
+        }
+        let states = self
+            .max_timer()
+            .checked_add(1)
+            .and_then(|timers| timers.checked_mul(self.lifespan.unwrap_or(1)));
+        match states {
+            Some(states) if states <= MAX_STATES => Ok(()),
+            _ => Err(format!(
+                "Too many fish states, at most {} are supported",
+                MAX_STATES
+            )),
+        }
+    }
+
+    pub fn max_timer(&self) -> usize {
+        self.new_timer.max(self.reset_timer)
+    }
+
+    /// Number of distinct fish states, i.e. the size of a [`crate::Fish`] histogram. The model
+    /// must be valid.
+    pub fn states(&self) -> usize {
+        (self.max_timer() + 1) * self.lifespan.unwrap_or(1)
+    }
+
+    /// State of a fish with the given timer, which has already spawned `cycles` times.
+    pub fn state(&self, timer: usize, cycles: usize) -> usize {
+        cycles * (self.max_timer() + 1) + timer
+    }
+
+    /// Timer of a fish in the given state.
+    pub fn timer(&self, state: usize) -> usize {
+        state % (self.max_timer() + 1)
+    }
+
+    /// States reached after one day by a fish in the given state, with the number of fish in
+    /// each of them.
+    pub fn transitions(&self, state: usize) -> Vec<(usize, usize)> {
+        let timer = self.timer(state);
+        let cycles = state / (self.max_timer() + 1);
+        if timer > 0 {
+            return vec![(state - 1, 1)];
+        }
+        let mut transitions = vec![(self.state(self.new_timer, 0), self.offspring)];
+        match self.lifespan {
+            None => transitions.push((self.state(self.reset_timer, 0), 1)),
+            Some(lifespan) if cycles + 1 < lifespan => {
+                transitions.push((self.state(self.reset_timer, cycles + 1), 1))
+            }
+            Some(_) => {}
+        }
+        transitions
+    }
+
+    /// Simulates one day, or returns `None` if a number of fish overflows.
+    pub fn step<T: FishCount>(&self, fish: &[T]) -> Option<Vec<T>> {
+        let mut next = vec![T::default(); self.states()];
+        for (state, count) in fish.iter().enumerate() {
+            for (target, factor) in self.transitions(state) {
+                next[target] = next[target].checked_add(&count.checked_mul(factor)?)?;
+            }
+        }
+        Some(next)
+    }
+
+    /// Number of fish with each timer, whatever the number of times they have spawned, or
+    /// `None` if a number of fish overflows.
+    pub fn timer_histogram<T: FishCount>(&self, fish: &[T]) -> Option<Vec<T>> {
+        let mut histogram = vec![T::default(); self.max_timer() + 1];
+        for (state, count) in fish.iter().enumerate() {
+            let timer = self.timer(state);
+            histogram[timer] = histogram[timer].checked_add(count)?;
+        }
+        Some(histogram)
+    }
+}
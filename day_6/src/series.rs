@@ -0,0 +1,101 @@
+use crate::count::FishCount;
+use crate::model::SpawnModel;
+use crate::{count_fish, Fish};
+use std::fmt::Write;
+
+/// Iterator over the number of fish with each timer, day after day, starting with the initial
+/// fish on day 0. If a number of fish overflows, the error is the last item.
+pub struct Population<'a, T> {
+    model: &'a SpawnModel,
+    next_fish: Option<Result<Fish<T>, String>>,
+    next_day: usize,
+}
+
+impl<'a, T: FishCount> Population<'a, T> {
+    pub fn new(model: &'a SpawnModel, initial_fish: &[T]) -> Population<'a, T> {
+        Population {
+            model,
+            next_fish: Some(Ok(initial_fish.to_vec())),
+            next_day: 0,
+        }
+    }
+}
+
+impl<T: FishCount> Iterator for Population<'_, T> {
+    type Item = Result<Vec<T>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let fish = match self.next_fish.take()? {
+            Ok(fish) => fish,
+            Err(err) => return Some(Err(err)),
+        };
+        let day = self.next_day;
+        self.next_day += 1;
+        let overflow = |day| format!("Number of fish overflowed on day {}", day);
+        let histogram = self
+            .model
+            .timer_histogram(&fish)
+            .ok_or_else(|| overflow(day));
+        if histogram.is_ok() {
+            self.next_fish = Some(self.model.step(&fish).ok_or_else(|| overflow(day + 1)));
+        }
+        Some(histogram)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    /// Average relative growth of the population per day, e.g. `0.1` for 10% more fish each day.
+    pub daily_growth_rate: f64,
+    /// Number of days for the population to double at the average growth rate, if it grows.
+    pub doubling_time: Option<f64>,
+}
+
+/// Summarizes the evolution of the total population, given the total for each day. `None` if
+/// there are less than two days or no initial fish.
+pub fn summarize<T: FishCount>(totals: &[T]) -> Option<Summary> {
+    let (first, last) = (totals.first()?.to_f64(), totals.last()?.to_f64());
+    if totals.len() < 2 || first == 0.0 {
+        return None;
+    }
+    let daily_growth_rate = (last / first).powf(1.0 / (totals.len() - 1) as f64) - 1.0;
+    let doubling_time = if daily_growth_rate > 0.0 {
+        Some(2f64.ln() / daily_growth_rate.ln_1p())
+    } else {
+        None
+    };
+    Some(Summary { daily_growth_rate, doubling_time })
+}
+
+/// First day on which the total population is strictly greater than `threshold`, or `None` if
+/// it never is within the given daily histograms.
+pub fn first_day_exceeding<T: FishCount + PartialOrd>(
+    histograms: impl IntoIterator<Item = Result<Vec<T>, String>>,
+    threshold: &T,
+) -> Result<Option<usize>, String> {
+    for (day, histogram) in histograms.into_iter().enumerate() {
+        if count_fish(&histogram?)? > *threshold {
+            return Ok(Some(day));
+        }
+    }
+    Ok(None)
+}
+
+/// One row per day, with the day, the total population, then the number of fish with each
+/// timer.
+pub fn to_csv<T: FishCount>(histograms: &[Vec<T>]) -> Result<String, String> {
+    let timers = histograms.first().map_or(0, |histogram| histogram.len());
+    let mut csv = String::from("day,total");
+    for timer in 0..timers {
+        write!(csv, ",timer_{}", timer).unwrap();
+    }
+    csv.push('\n');
+    for (day, histogram) in histograms.iter().enumerate() {
+        write!(csv, "{},{}", day, count_fish(histogram)?).unwrap();
+        for count in histogram {
+            write!(csv, ",{}", count).unwrap();
+        }
+        csv.push('\n');
+    }
+    Ok(csv)
+}
@@ -0,0 +1,179 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+
+/// Fuel needed by a single crab to move.
+///
+/// The fuel must not decrease when the crab moves further away in the same direction, so the
+/// best target is always between the leftmost and the rightmost crab.
+pub trait FuelCost {
+    /// Fuel for one crab to move from position `from` to position `to`, or `None` if it
+    /// overflows.
+    fn fuel(&self, from: usize, to: usize) -> Option<usize>;
+
+    /// Whether the fuel of a crab is a convex function of its target. The total fuel is then
+    /// convex too, and can be minimized by a fast search rather than by trying every target.
+    fn is_convex(&self) -> bool;
+
+    /// Targets within `targets` where the fuel of a crab at `from` may stop being convex. Between
+    /// two consecutive breakpoints, not counting `from`, the fuel is either convex or concave.
+    fn breakpoints(&self, _from: usize, _targets: RangeInclusive<usize>) -> Vec<usize> {
+        Vec::new()
+    }
+}
+
+/// One unit of fuel per step (part 1).
+pub struct Linear;
+
+impl FuelCost for Linear {
+    fn fuel(&self, from: usize, to: usize) -> Option<usize> {
+        Some(from.abs_diff(to))
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+/// The n-th step costs n units of fuel (part 2).
+pub struct Triangular;
+
+impl FuelCost for Triangular {
+    fn fuel(&self, from: usize, to: usize) -> Option<usize> {
+        // https://en.wikipedia.org/wiki/1_%2B_2_%2B_3_%2B_4_%2B_%E2%8B%AF#Partial_sums
+        let distance = from.abs_diff(to) as u128;
+        usize::try_from(distance * (distance + 1) / 2).ok()
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+/// `square * distance^2 + linear * distance` units of fuel.
+pub struct Quadratic {
+    pub square: usize,
+    pub linear: usize,
+}
+
+impl FuelCost for Quadratic {
+    fn fuel(&self, from: usize, to: usize) -> Option<usize> {
+        let distance = from.abs_diff(to);
+        let square = self.square.checked_mul(distance)?.checked_mul(distance)?;
+        square.checked_add(self.linear.checked_mul(distance)?)
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+/// Another cost, but never more than `cap` units of fuel.
+pub struct Capped {
+    pub cost: Box<dyn FuelCost>,
+    pub cap: usize,
+}
+
+impl FuelCost for Capped {
+    fn fuel(&self, from: usize, to: usize) -> Option<usize> {
+        // Past the cap, an overflowing fuel is just the cap.
+        Some(
+            self.cost
+                .fuel(from, to)
+                .map_or(self.cap, |fuel| fuel.min(self.cap)),
+        )
+    }
+
+    fn is_convex(&self) -> bool {
+        // Capping flattens the cost far away from the crab, which breaks convexity.
+        false
+    }
+
+    fn breakpoints(&self, from: usize, targets: RangeInclusive<usize>) -> Vec<usize> {
+        let below_cap = |to| {
+            self.cost
+                .fuel(from, to)
+                .is_some_and(|fuel| fuel <= self.cap)
+        };
+        let (first, last) = targets.clone().into_inner();
+        // The furthest targets on each side below the cap, by binary search.
+        let (mut low, mut high) = (first.min(from), from);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if below_cap(middle) {
+                high = middle;
+            } else {
+                low = middle + 1;
+            }
+        }
+        let left = low;
+        let (mut low, mut high) = (from, last.max(from));
+        while low < high {
+            let middle = high - (high - low) / 2;
+            if below_cap(middle) {
+                low = middle;
+            } else {
+                high = middle - 1;
+            }
+        }
+        let right = high;
+        // The fuel is flat beyond them, and convex between them.
+        let mut breakpoints = self.cost.breakpoints(from, targets);
+        breakpoints.extend([left.saturating_sub(1), left, right, right.saturating_add(1)]);
+        breakpoints
+    }
+}
+
+/// Another cost, multiplied by a weight depending on the initial position of the crab (1 for
+/// the positions without a specified weight).
+pub struct Weighted {
+    pub cost: Box<dyn FuelCost>,
+    pub weights: HashMap<usize, usize>,
+}
+
+impl FuelCost for Weighted {
+    fn fuel(&self, from: usize, to: usize) -> Option<usize> {
+        self.weights
+            .get(&from)
+            .unwrap_or(&1)
+            .checked_mul(self.cost.fuel(from, to)?)
+    }
+
+    fn is_convex(&self) -> bool {
+        self.cost.is_convex()
+    }
+
+    fn breakpoints(&self, from: usize, targets: RangeInclusive<usize>) -> Vec<usize> {
+        self.cost.breakpoints(from, targets)
+    }
+}
+
+/// Different costs for moving left (towards position 0) and right.
+pub struct Asymmetric {
+    pub left: Box<dyn FuelCost>,
+    pub right: Box<dyn FuelCost>,
+}
+
+impl FuelCost for Asymmetric {
+    fn fuel(&self, from: usize, to: usize) -> Option<usize> {
+        if to < from {
+            self.left.fuel(from, to)
+        } else {
+            self.right.fuel(from, to)
+        }
+    }
+
+    fn is_convex(&self) -> bool {
+        // Both sides are minimal (zero fuel) where they meet, so they join into a convex cost.
+        self.left.is_convex() && self.right.is_convex()
+    }
+
+    fn breakpoints(&self, from: usize, targets: RangeInclusive<usize>) -> Vec<usize> {
+        let left = self.left.breakpoints(from, targets.clone());
+        let right = self.right.breakpoints(from, targets);
+        left.into_iter()
+            .filter(|&to| to < from)
+            .chain(right.into_iter().filter(|&to| to > from))
+            .collect()
+    }
+}
@@ -1,24 +1,173 @@
+use crate::cost::{Asymmetric, Capped, FuelCost, Linear, Quadratic, Triangular, Weighted};
+use crate::solver::{align, align_linear, align_triangular};
+use crate::space::{align_euclidean, align_separable, dimensions, Point};
+use std::collections::HashMap;
+
+mod cost;
+mod solver;
+mod space;
+
 static INPUT: &str = include_str!("input");
 
-/// Index = crab position, value = number of crabs with that position.
-/// E.g. if `crabs[2] == 5`, there are 5 crabs at position 2.
-type Crabs = [usize];
+/// Distinct crab positions, in increasing order, with the number of crabs at each of them.
+/// E.g. `[(2, 5), (16, 1)]` means that there are 5 crabs at position 2 and 1 at position 16.
+type Crabs = [(usize, usize)];
 
 fn main() -> Result<(), String> {
+    let options = parse_args(std::env::args().skip(1))?;
     let initial_positions = parse_input(INPUT)?;
 
-    let part_1_result = lowest_fuel_expenditure(&initial_positions, linear_fuel_cost)
-        .ok_or("Result 1 not found")?;
-    println!("Part 1 result: {}", part_1_result);
+    let part_1_result = align_linear(&initial_positions).ok_or("Result 1 not found")?;
+    println!(
+        "Part 1 result: {} (position {})",
+        part_1_result.fuel, part_1_result.position
+    );
+
+    let part_2_result = align_triangular(&initial_positions).ok_or("Result 2 not found")?;
+    println!(
+        "Part 2 result: {} (position {})",
+        part_2_result.fuel, part_2_result.position
+    );
+
+    if let Some(fuel_cost) = options.fuel_cost {
+        let result = align(&initial_positions, fuel_cost.as_ref()).ok_or("Result not found")?;
+        println!(
+            "Custom fuel cost result: {} (position {})",
+            result.fuel, result.position
+        );
+    }
 
-    let part_2_result = lowest_fuel_expenditure(&initial_positions, actual_fuel_cost)
-        .ok_or("Result 1 not found")?;
-    println!("Part 2 result: {}", part_2_result);
+    if let Some(file) = &options.points_file {
+        let input = std::fs::read_to_string(file)
+            .map_err(|err| format!("Cannot read {}: {}", file, err))?;
+        let crabs = parse_points(&input)?;
+        if let SpaceCost::Euclidean = options.space_cost {
+            let result = align_euclidean(&crabs)?;
+            println!(
+                "Euclidean result: {:.3} (position {:.3?})",
+                result.distance, result.position
+            );
+        } else {
+            let axis_costs = match &options.space_cost {
+                SpaceCost::PerAxis(costs) => costs.iter().map(|cost| cost.as_ref()).collect(),
+                _ => vec![&Linear as &dyn FuelCost; dimensions(&crabs)?],
+            };
+            let result = align_separable(&crabs, &axis_costs)?;
+            println!(
+                "Multi-dimensional result: {} (position {:?})",
+                result.fuel, result.position
+            );
+        }
+    }
 
     Ok(())
 }
 
-fn parse_input(input: &str) -> Result<Vec<usize>, String> {
+/// How crabs with several coordinates spend fuel.
+enum SpaceCost {
+    /// Linear cost on every axis.
+    Manhattan,
+    /// Euclidean distance between the crab and its target.
+    Euclidean,
+    /// Sum of a fuel cost model on each axis.
+    PerAxis(Vec<Box<dyn FuelCost>>),
+}
+
+/// Command line arguments: `[--model MODEL] [--right-model MODEL] [--cap N]
+/// [--weight POSITION:WEIGHT]... [--points FILE] [--space-cost SPACE_COST]`.
+struct Options {
+    /// Custom fuel cost for the puzzle crabs, if any option defines one.
+    fuel_cost: Option<Box<dyn FuelCost>>,
+    /// File with one crab per line, with its comma-separated coordinates.
+    points_file: Option<String>,
+    space_cost: SpaceCost,
+}
+
+/// `MODEL` is `linear`, `triangular` or `quadratic:SQUARE:LINEAR`. `--model` alone applies to
+/// moves in both directions, and only to moves to the left if `--right-model` is specified.
+/// `SPACE_COST` is `manhattan` (default), `euclidean`, or one comma-separated `MODEL` per axis.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let (mut model, mut right_model, mut cap) = (None, None, None);
+    let mut weights = HashMap::new();
+    let (mut points_file, mut space_cost) = (None, SpaceCost::Manhattan);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("Missing value for {}", arg))?;
+        match arg.as_str() {
+            "--points" => points_file = Some(value),
+            "--space-cost" => space_cost = parse_space_cost(&value)?,
+            "--model" => model = Some(parse_model(&value)?),
+            "--right-model" => right_model = Some(parse_model(&value)?),
+            "--cap" => cap = Some(parse_number(&value)?),
+            "--weight" => {
+                let (position, weight) = value
+                    .split_once(':')
+                    .ok_or(format!("Invalid weight {}", value))?;
+                weights.insert(parse_number(position)?, parse_number(weight)?);
+            }
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    let fuel_cost =
+        if model.is_none() && right_model.is_none() && cap.is_none() && weights.is_empty() {
+            None
+        } else {
+            Some(combine_fuel_costs(model, right_model, weights, cap))
+        };
+    Ok(Options { fuel_cost, points_file, space_cost })
+}
+
+fn combine_fuel_costs(
+    model: Option<Box<dyn FuelCost>>,
+    right_model: Option<Box<dyn FuelCost>>,
+    weights: HashMap<usize, usize>,
+    cap: Option<usize>,
+) -> Box<dyn FuelCost> {
+    let mut fuel_cost = model.unwrap_or_else(|| Box::new(Linear));
+    if let Some(right) = right_model {
+        fuel_cost = Box::new(Asymmetric { left: fuel_cost, right });
+    }
+    if !weights.is_empty() {
+        fuel_cost = Box::new(Weighted { cost: fuel_cost, weights });
+    }
+    if let Some(cap) = cap {
+        fuel_cost = Box::new(Capped { cost: fuel_cost, cap });
+    }
+    fuel_cost
+}
+
+fn parse_space_cost(space_cost: &str) -> Result<SpaceCost, String> {
+    match space_cost {
+        "manhattan" => Ok(SpaceCost::Manhattan),
+        "euclidean" => Ok(SpaceCost::Euclidean),
+        models => Ok(SpaceCost::PerAxis(
+            models
+                .split(',')
+                .map(parse_model)
+                .collect::<Result<_, _>>()?,
+        )),
+    }
+}
+
+fn parse_model(model: &str) -> Result<Box<dyn FuelCost>, String> {
+    let parts = model.split(':').collect::<Vec<_>>();
+    match parts.as_slice() {
+        ["linear"] => Ok(Box::new(Linear)),
+        ["triangular"] => Ok(Box::new(Triangular)),
+        ["quadratic", square, linear] => Ok(Box::new(Quadratic {
+            square: parse_number(square)?,
+            linear: parse_number(linear)?,
+        })),
+        _ => Err(format!("Unknown fuel cost model {}", model)),
+    }
+}
+
+fn parse_number(number: &str) -> Result<usize, String> {
+    number
+        .parse::<usize>()
+        .map_err(|err| format!("Invalid number {}: {}", number, err))
+}
+
+fn parse_input(input: &str) -> Result<Vec<(usize, usize)>, String> {
     let crab_positions = input
         .lines()
         .next()
@@ -31,70 +180,314 @@ fn parse_input(input: &str) -> Result<Vec<usize>, String> {
     Ok(crabs)
 }
 
-fn crab_positions_to_distribution(crab_positions: &[usize]) -> Result<Vec<usize>, String> {
-    let max_position = crab_positions.iter().max().ok_or("Empty input list")?;
-    let mut all_crabs: Vec<usize> = vec![0; max_position + 1];
-    for crab_position in crab_positions {
-        all_crabs[*crab_position] += 1;
+fn parse_points(input: &str) -> Result<Vec<Point>, String> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(index, line)| {
+            line.split(',')
+                .map(|coordinate| coordinate.trim().parse::<usize>())
+                .collect::<Result<Point, _>>()
+                .map_err(|err| format!("Line {}: {}", index + 1, err))
+        })
+        .collect()
+}
+
+fn crab_positions_to_distribution(crab_positions: &[usize]) -> Result<Vec<(usize, usize)>, String> {
+    if crab_positions.is_empty() {
+        return Err("Empty input list".to_owned());
+    }
+    let mut sorted_positions = crab_positions.to_vec();
+    sorted_positions.sort_unstable();
+    let mut all_crabs: Vec<(usize, usize)> = Vec::new();
+    for crab_position in sorted_positions {
+        match all_crabs.last_mut() {
+            Some((position, count)) if *position == crab_position => *count += 1,
+            _ => all_crabs.push((crab_position, 1)),
+        }
     }
     Ok(all_crabs)
 }
 
-fn lowest_fuel_expenditure(
-    initial_positions: &Crabs,
-    fuel_cost: fn(usize) -> usize,
-) -> Option<usize> {
-    let calculate_fuel_expenditure = |target: usize| -> usize {
-        initial_positions
-            .iter()
-            .enumerate()
-            .fold(0, |acc, (pos, count)| {
-                acc + count * fuel_cost(abs_diff(pos, target))
-            })
-    };
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::solver::{align_convex, total_fuel, Alignment};
+    use crate::space::SpaceAlignment;
 
-    (0..initial_positions.len())
-        .into_iter()
-        .map(calculate_fuel_expenditure)
-        .min()
-}
+    static TEST_CRABS: [usize; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
 
-fn linear_fuel_cost(distance: usize) -> usize {
-    distance
-}
+    /// Reference implementation: tries every position.
+    fn brute_force(crabs: &Crabs, fuel_cost: &dyn FuelCost) -> Option<Alignment> {
+        let &(max_position, _) = crabs.last()?;
+        (0..=max_position)
+            .filter_map(|position| {
+                let fuel = total_fuel(crabs, position, fuel_cost)?;
+                Some(Alignment { position, fuel })
+            })
+            .min_by_key(|alignment| alignment.fuel)
+    }
 
-fn actual_fuel_cost(distance: usize) -> usize {
-    // https://en.wikipedia.org/wiki/1_%2B_2_%2B_3_%2B_4_%2B_%E2%8B%AF#Partial_sums
-    distance * (distance + 1) / 2
-}
+    struct Cubic;
+
+    impl FuelCost for Cubic {
+        fn fuel(&self, from: usize, to: usize) -> Option<usize> {
+            from.abs_diff(to).checked_pow(3)
+        }
 
-fn abs_diff(x: usize, y: usize) -> usize {
-    if x < y {
-        y - x
-    } else {
-        x - y
+        fn is_convex(&self) -> bool {
+            true
+        }
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Fuel growing slower and slower with the distance.
+    struct SquareRoot;
 
-    static TEST_CRABS: [usize; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+    impl FuelCost for SquareRoot {
+        fn fuel(&self, from: usize, to: usize) -> Option<usize> {
+            Some(((from.abs_diff(to) as f64).sqrt() * 100.0) as usize)
+        }
+
+        fn is_convex(&self) -> bool {
+            false
+        }
+    }
+
+    /// Deterministic pseudo-random crab distributions.
+    fn random_crabs(count: usize) -> Vec<Vec<(usize, usize)>> {
+        let mut random = common::random_numbers(42);
+        let mut next = |bound| random(bound) as usize;
+        (0..count)
+            .map(|_| {
+                let crabs = next(20) + 1;
+                let max_position = next(100) as u64 + 1;
+                let positions = (0..crabs).map(|_| next(max_position)).collect::<Vec<_>>();
+                crab_positions_to_distribution(&positions).unwrap()
+            })
+            .collect()
+    }
 
     #[test]
     fn test_part_1() {
         let crabs = crab_positions_to_distribution(&TEST_CRABS).unwrap();
-        let actual = lowest_fuel_expenditure(&crabs, linear_fuel_cost).unwrap();
-        let expected: usize = 37;
+        let actual = align_linear(&crabs).unwrap();
+        let expected = Alignment { position: 2, fuel: 37 };
         assert_eq!(expected, actual);
     }
 
     #[test]
     fn test_part_2() {
         let crabs = crab_positions_to_distribution(&TEST_CRABS).unwrap();
-        let actual = lowest_fuel_expenditure(&crabs, actual_fuel_cost).unwrap();
-        let expected: usize = 168;
+        let actual = align_triangular(&crabs).unwrap();
+        let expected = Alignment { position: 5, fuel: 168 };
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_solvers_match_brute_force() {
+        for crabs in random_crabs(200) {
+            let full_range = 0..=crabs.last().unwrap().0;
+            let linear = brute_force(&crabs, &Linear).unwrap();
+            assert_eq!(linear.fuel, align_linear(&crabs).unwrap().fuel);
+            let triangular = brute_force(&crabs, &Triangular).unwrap();
+            assert_eq!(triangular.fuel, align_triangular(&crabs).unwrap().fuel);
+            let cubic = brute_force(&crabs, &Cubic).unwrap();
+            let actual = align_convex(&crabs, &Cubic, full_range).unwrap();
+            assert_eq!(cubic, actual);
+        }
+        assert_eq!(None, align_linear(&[(3, 0)]));
+        assert_eq!(None, align_triangular(&[]));
+    }
+
+    #[test]
+    fn test_huge_positions() {
+        let crabs = crab_positions_to_distribution(&[1_000_000_000, 0, 7, 7]).unwrap();
+        assert_eq!(vec![(0, 1), (7, 2), (1_000_000_000, 1)], crabs);
+        let expected = Alignment { position: 7, fuel: 1_000_000_000 };
+        assert_eq!(Some(expected), align_linear(&crabs));
+        let expected = Alignment { position: 250_000_003, fuel: 374_999_997_250_000_021 };
+        assert_eq!(Some(expected), align_triangular(&crabs));
+        let capped = Capped { cost: Box::new(Linear), cap: 10 };
+        let crabs = crab_positions_to_distribution(&[0, 1_000_000_000, 1_000_000_003]).unwrap();
+        let expected = Alignment { position: 1_000_000_000, fuel: 13 };
+        assert_eq!(Some(expected), align(&crabs, &capped));
+
+        // The total fuel doesn't fit, whichever the target.
+        let crabs = [(0, 100), (1_000_000_000, 100)];
+        assert_eq!(None, align_triangular(&crabs));
+        assert_eq!(None, align(&crabs, &Triangular));
+        assert_eq!(None, total_fuel(&crabs, 500_000_000, &Triangular));
+        let quadratic = Quadratic { square: 1 << 40, linear: 0 };
+        assert_eq!(None, quadratic.fuel(0, 1 << 20));
+        // Far away targets overflow, but not the best one.
+        let crabs = [(0, 1), (usize::MAX / 2, 1), (usize::MAX, 1)];
+        let expected = Alignment { position: usize::MAX / 2, fuel: usize::MAX };
+        assert_eq!(Some(expected), align_linear(&crabs));
+        let crabs = [(1 << 32, 1), (3 << 31, 1)];
+        let expected = Alignment {
+            position: 5 << 30,
+            fuel: 2 * ((1 << 30) * ((1 << 30) + 1) / 2),
+        };
+        assert_eq!(
+            Some(expected),
+            align_convex(&crabs, &Triangular, 0..=usize::MAX)
+        );
+        assert_eq!(None, total_fuel(&crabs, 0, &Triangular));
+        let cubic = align_convex(&[(0, 1), (2_000_000, 1)], &Cubic, 0..=usize::MAX);
+        assert_eq!(
+            Some(Alignment { position: 1_000_000, fuel: 2_000_000_000_000_000_000 }),
+            cubic
+        );
+        let capped = Capped { cost: Box::new(Triangular), cap: 10 };
+        assert_eq!(Some(10), capped.fuel(0, usize::MAX));
+
+        assert_eq!(
+            Err("Empty input list".to_owned()),
+            crab_positions_to_distribution(&[])
+        );
+    }
+
+    #[test]
+    fn test_fuel_cost_models() {
+        let args = |args: &str| {
+            parse_args(args.split(' ').map(|arg| arg.to_owned())).map(|options| options.fuel_cost)
+        };
+        let models = [
+            "--model linear",
+            "--model triangular",
+            "--model quadratic:3:2",
+            "--model linear --cap 7",
+            "--model triangular --cap 40",
+            "--model quadratic:1:0 --right-model linear",
+            "--right-model triangular",
+            "--model triangular --weight 0:5 --weight 3:2",
+            "--model linear --right-model quadratic:0:3 --weight 1:4 --cap 10",
+        ];
+        for model in models {
+            let fuel_cost = args(model).unwrap().unwrap();
+            for crabs in random_crabs(50) {
+                let expected = brute_force(&crabs, fuel_cost.as_ref()).unwrap().fuel;
+                let actual = align(&crabs, fuel_cost.as_ref()).unwrap().fuel;
+                assert_eq!(expected, actual, "{} {:?}", model, crabs);
+            }
+        }
+
+        let crabs = crab_positions_to_distribution(&TEST_CRABS).unwrap();
+        let cheap_left = args("--model linear --right-model quadratic:10:0")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Some(Alignment { position: 0, fuel: 49 }),
+            align(&crabs, cheap_left.as_ref())
+        );
+        let capped = args("--model triangular --cap 20").unwrap().unwrap();
+        assert!(!capped.is_convex());
+        assert_eq!(
+            Some(Alignment { position: 2, fuel: 63 }),
+            align(&crabs, capped.as_ref())
+        );
+
+        // A concave cost, without breakpoints: the best position is on a crab.
+        let crabs = [(20, 2), (26, 1), (59, 1), (115, 1), (190, 1), (191, 1)];
+        let expected = brute_force(&crabs, &SquareRoot).unwrap();
+        assert_eq!(4452, expected.fuel);
+        assert_eq!(Some(expected), align(&crabs, &SquareRoot));
+        for crabs in random_crabs(50) {
+            let expected = brute_force(&crabs, &SquareRoot).unwrap();
+            assert_eq!(Some(expected), align(&crabs, &SquareRoot), "{:?}", crabs);
+        }
+
+        assert!(args("--model cubic").is_err());
+        assert!(args("--cap").is_err());
+        assert!(args("--weight 3").is_err());
+        assert!(parse_args(std::iter::empty()).unwrap().fuel_cost.is_none());
+    }
+
+    #[test]
+    fn test_multi_dimensional() {
+        let crabs = parse_points("0,0\n4,1\n1,5\n2,2\n9,9\n").unwrap();
+        let manhattan = align_separable(&crabs, &[&Linear, &Linear]).unwrap();
+        let expected = SpaceAlignment { position: vec![2, 2], fuel: 25 };
+        assert_eq!(expected, manhattan);
+        let per_axis = align_separable(&crabs, &[&Linear, &Triangular]).unwrap();
+        let expected = SpaceAlignment { position: vec![2, 3], fuel: 12 + 34 };
+        assert_eq!(expected, per_axis);
+
+        // Brute force over the whole grid, in 3D.
+        let crabs = parse_points("3,0,7\n1,1,1\n6,2,0\n0,5,2\n2,2,2\n").unwrap();
+        let costs: [&dyn FuelCost; 3] = [&Triangular, &Linear, &Quadratic { square: 2, linear: 1 }];
+        let actual = align_separable(&crabs, &costs).unwrap();
+        let mut best = usize::MAX;
+        for x in 0..8 {
+            for y in 0..8 {
+                for z in 0..8 {
+                    let fuel = crabs
+                        .iter()
+                        .map(|crab| {
+                            costs[0].fuel(crab[0], x).unwrap()
+                                + costs[1].fuel(crab[1], y).unwrap()
+                                + costs[2].fuel(crab[2], z).unwrap()
+                        })
+                        .sum();
+                    best = best.min(fuel);
+                }
+            }
+        }
+        assert_eq!(best, actual.fuel);
+
+        assert!(align_separable(&crabs, &[&Linear]).is_err());
+        assert!(align_separable(&[vec![1, 2], vec![3]], &[&Linear, &Linear]).is_err());
+        assert!(align_euclidean(&[]).is_err());
+        assert!(parse_points("1,2\n3,x\n").is_err());
+    }
+
+    #[test]
+    fn test_euclidean() {
+        // The geometric median of the corners of a square is its center.
+        let square = parse_points("0,0\n0,2\n2,0\n2,2").unwrap();
+        let actual = align_euclidean(&square).unwrap();
+        assert!((actual.position[0] - 1.0).abs() < 1e-6);
+        assert!((actual.position[1] - 1.0).abs() < 1e-6);
+        assert!((actual.distance - 4.0 * 2f64.sqrt()).abs() < 1e-6);
+
+        // With an outlier, the median stays on the crab where most of the others are.
+        let crabs = parse_points("5,5\n5,5\n5,5\n4,5\n100,100").unwrap();
+        let actual = align_euclidean(&crabs).unwrap();
+        assert!((actual.position[0] - 5.0).abs() < 1e-3);
+        assert!((actual.position[1] - 5.0).abs() < 1e-3);
+
+        // The optimum is exactly on a crab: the iteration stops there instead of oscillating.
+        let crabs = parse_points("0,0\n0,0\n10,0\n0,10").unwrap();
+        let actual = align_euclidean(&crabs).unwrap();
+        assert!(actual
+            .position
+            .iter()
+            .all(|coordinate| coordinate.abs() < 1e-6));
+        assert!((actual.distance - 20.0).abs() < 1e-6);
+        let crabs = parse_points("0,0\n1,0\n2,0").unwrap();
+        assert_eq!(vec![1.0, 0.0], align_euclidean(&crabs).unwrap().position);
+        let crabs = parse_points("0,0\n0,0\n0,0\n4,0\n1,0").unwrap();
+        let actual = align_euclidean(&crabs).unwrap();
+        assert!(actual
+            .position
+            .iter()
+            .all(|coordinate| coordinate.abs() < 1e-6));
+
+        // No point of a fine grid is better.
+        let crabs = parse_points("0,0\n10,0\n3,8\n7,2").unwrap();
+        let actual = align_euclidean(&crabs).unwrap();
+        for x in 0..=100 {
+            for y in 0..=100 {
+                let (x, y) = (x as f64 / 10.0, y as f64 / 10.0);
+                let distance: f64 = crabs
+                    .iter()
+                    .map(|crab| {
+                        ((crab[0] as f64 - x).powi(2) + (crab[1] as f64 - y).powi(2)).sqrt()
+                    })
+                    .sum();
+                assert!(actual.distance <= distance + 1e-9);
+            }
+        }
+    }
 }
@@ -0,0 +1,130 @@
+use crate::cost::{FuelCost, Linear, Triangular};
+use crate::Crabs;
+use std::convert::TryFrom;
+use std::ops::RangeInclusive;
+
+/// Target position for all the crabs, and the total fuel they need to reach it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Alignment {
+    pub position: usize,
+    pub fuel: usize,
+}
+
+/// Total fuel for all the crabs to move to `target`, or `None` if it overflows.
+pub fn total_fuel(crabs: &Crabs, target: usize, fuel_cost: &dyn FuelCost) -> Option<usize> {
+    usize::try_from(exact_total_fuel(crabs, target, fuel_cost)?).ok()
+}
+
+/// Total fuel for all the crabs to move to `target`, or `None` if the fuel of a crab overflows.
+/// The sum itself doesn't overflow, unless there are more than `usize::MAX` crabs.
+fn exact_total_fuel(crabs: &Crabs, target: usize, fuel_cost: &dyn FuelCost) -> Option<u128> {
+    crabs.iter().try_fold(0u128, |total, &(position, count)| {
+        let fuel = fuel_cost.fuel(position, target)? as u128 * count as u128;
+        total.checked_add(fuel)
+    })
+}
+
+/// Best alignment for any fuel cost: by convex search if the cost is convex, otherwise by convex
+/// search between each pair of consecutive crabs or breakpoints of their fuel, and on the crabs
+/// and breakpoints themselves, in O(n^2 log(range)).
+pub fn align(crabs: &Crabs, fuel_cost: &dyn FuelCost) -> Option<Alignment> {
+    let range = crabs.first()?.0..=crabs.last()?.0;
+    if fuel_cost.is_convex() {
+        return align_convex(crabs, fuel_cost, range);
+    }
+    let mut candidates = crabs
+        .iter()
+        .flat_map(|&(position, _)| {
+            let breakpoints = fuel_cost.breakpoints(position, range.clone());
+            std::iter::once(position).chain(breakpoints)
+        })
+        .filter(|position| range.contains(position))
+        .collect::<Vec<_>>();
+    candidates.sort_unstable();
+    candidates.dedup();
+    if candidates.len() == 1 {
+        return align_convex(crabs, fuel_cost, range);
+    }
+    // Between consecutive candidates, the total fuel is either convex, with its minimum found by
+    // convex search, or concave, with its minimum on one of the candidates. Ties go to the
+    // leftmost position.
+    let at_candidates = candidates.iter().filter_map(|&position| {
+        let fuel = total_fuel(crabs, position, fuel_cost)?;
+        Some(Alignment { position, fuel })
+    });
+    candidates
+        .windows(2)
+        .filter_map(|pair| align_convex(crabs, fuel_cost, pair[0]..=pair[1]))
+        .chain(at_candidates)
+        .min_by_key(|alignment| (alignment.fuel, alignment.position))
+}
+
+/// Best alignment with the linear fuel cost, which is exactly the median position.
+pub fn align_linear(crabs: &Crabs) -> Option<Alignment> {
+    let total_crabs: usize = crabs.iter().map(|(_, count)| count).sum();
+    // Lower median: first position with at least half of the crabs on it or before it.
+    let mut crabs_before = 0;
+    let (position, _) = *crabs.iter().find(|(_, count)| {
+        crabs_before += count;
+        total_crabs > 0 && 2 * crabs_before >= total_crabs
+    })?;
+    let fuel = total_fuel(crabs, position, &Linear)?;
+    Some(Alignment { position, fuel })
+}
+
+/// Best alignment with the triangular fuel cost.
+///
+/// The total fuel is `n/2 * (x - mean)^2` plus a term whose slope is at most `n/2`, so the best
+/// position is within 1/2 of the mean and only the few integers around it need to be searched.
+pub fn align_triangular(crabs: &Crabs) -> Option<Alignment> {
+    let total_crabs: usize = crabs.iter().map(|(_, count)| count).sum();
+    let &(max_position, _) = crabs.last()?;
+    if total_crabs == 0 {
+        return None;
+    }
+    let weighted_sum: u128 = crabs
+        .iter()
+        .map(|&(position, count)| position as u128 * count as u128)
+        .sum();
+    let mean = (weighted_sum / total_crabs as u128) as usize;
+    let range = mean.saturating_sub(1)..=(mean + 2).min(max_position);
+    align_convex(crabs, &Triangular, range)
+}
+
+/// Best alignment within `range` for a convex fuel cost, by binary search on the slope of the
+/// total fuel, in O(n log(range)). `None` if the best total fuel overflows.
+pub fn align_convex(
+    crabs: &Crabs,
+    fuel_cost: &dyn FuelCost,
+    range: RangeInclusive<usize>,
+) -> Option<Alignment> {
+    if range.is_empty() || crabs.iter().all(|(_, count)| *count == 0) {
+        return None;
+    }
+    let fuel = |target| exact_total_fuel(crabs, target, fuel_cost);
+    // The fuel of a crab only grows as the target moves away from it, so if it overflows, the
+    // best target is on the crab's side.
+    let overflows_from_left = |target| {
+        crabs
+            .iter()
+            .any(|&(position, _)| position <= target && fuel_cost.fuel(position, target).is_none())
+    };
+    let (mut low, mut high) = range.into_inner();
+    // The total fuel decreases then increases: find where it stops decreasing.
+    while low < high {
+        let middle = low + (high - low) / 2;
+        let stops_decreasing = match (fuel(middle), fuel(middle + 1)) {
+            (Some(fuel), Some(next_fuel)) => fuel <= next_fuel,
+            (Some(_), None) => true,
+            (None, Some(_)) => false,
+            (None, None) => overflows_from_left(middle),
+        };
+        if stops_decreasing {
+            high = middle;
+        } else {
+            low = middle + 1;
+        }
+    }
+    let fuel = total_fuel(crabs, low, fuel_cost)?;
+    Some(Alignment { position: low, fuel })
+}
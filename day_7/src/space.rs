@@ -0,0 +1,151 @@
+use crate::cost::FuelCost;
+use crate::crab_positions_to_distribution;
+use crate::solver::align;
+
+/// Coordinates of a crab, one per axis.
+pub type Point = Vec<usize>;
+
+/// Meeting point for crabs moving along several axes, and the total fuel to reach it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpaceAlignment {
+    pub position: Point,
+    pub fuel: usize,
+}
+
+/// Meeting point minimizing the sum of the Euclidean distances travelled by the crabs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EuclideanAlignment {
+    pub position: Vec<f64>,
+    pub distance: f64,
+}
+
+const WEISZFELD_MAX_ITERATIONS: usize = 10_000;
+const WEISZFELD_TOLERANCE: f64 = 1e-9;
+
+/// Number of axes of the crabs, checking that they all have the same.
+pub fn dimensions(crabs: &[Point]) -> Result<usize, String> {
+    let dimensions = crabs.first().ok_or("No crabs")?.len();
+    match crabs.iter().find(|crab| crab.len() != dimensions) {
+        Some(crab) => Err(format!(
+            "Expected {} coordinates, got {:?}",
+            dimensions, crab
+        )),
+        None => Ok(dimensions),
+    }
+}
+
+/// Best meeting point when the fuel is the sum of a cost along each axis (e.g. the Manhattan
+/// distance with the linear cost on every axis). Such a cost is separable, so each axis is
+/// solved exactly and independently with the 1D solver.
+pub fn align_separable(
+    crabs: &[Point],
+    axis_costs: &[&dyn FuelCost],
+) -> Result<SpaceAlignment, String> {
+    let dimensions = dimensions(crabs)?;
+    if axis_costs.len() != dimensions {
+        return Err(format!(
+            "Expected {} axis costs, got {}",
+            dimensions,
+            axis_costs.len()
+        ));
+    }
+    let mut alignment = SpaceAlignment { position: Vec::with_capacity(dimensions), fuel: 0 };
+    for (axis, fuel_cost) in axis_costs.iter().enumerate() {
+        let coordinates = crabs.iter().map(|crab| crab[axis]).collect::<Vec<_>>();
+        let axis_alignment = align(&crab_positions_to_distribution(&coordinates)?, *fuel_cost)
+            .ok_or(format!("No alignment on axis {}", axis))?;
+        alignment.position.push(axis_alignment.position);
+        alignment.fuel = alignment
+            .fuel
+            .checked_add(axis_alignment.fuel)
+            .ok_or("Total fuel overflows")?;
+    }
+    Ok(alignment)
+}
+
+/// Geometric median of the crabs, by Weiszfeld's iteration starting from their centroid: each
+/// step moves to the average of the crabs weighted by the inverse of their distance. Crabs
+/// exactly at the current estimate can't be weighted this way: with the Vardi–Zhang
+/// modification, the estimate stays there if those crabs outweigh the pull of the others, and
+/// otherwise moves only partly towards the average.
+pub fn align_euclidean(crabs: &[Point]) -> Result<EuclideanAlignment, String> {
+    let dimensions = dimensions(crabs)?;
+    let crabs = crabs
+        .iter()
+        .map(|crab| {
+            crab.iter()
+                .map(|coordinate| *coordinate as f64)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut estimate = vec![0.0; dimensions];
+    for crab in &crabs {
+        for (axis, coordinate) in crab.iter().enumerate() {
+            estimate[axis] += coordinate / crabs.len() as f64;
+        }
+    }
+
+    let mut converged = false;
+    for _ in 0..WEISZFELD_MAX_ITERATIONS {
+        let mut weighted_sum = vec![0.0; dimensions];
+        let mut total_weight = 0.0;
+        let mut coinciding = 0.0;
+        for crab in &crabs {
+            let distance = euclidean_distance(crab, &estimate);
+            if distance < WEISZFELD_TOLERANCE {
+                coinciding += 1.0;
+                continue;
+            }
+            for (axis, coordinate) in crab.iter().enumerate() {
+                weighted_sum[axis] += coordinate / distance;
+            }
+            total_weight += 1.0 / distance;
+        }
+        if total_weight == 0.0 {
+            converged = true;
+            break;
+        }
+        let average = weighted_sum
+            .iter()
+            .map(|sum| sum / total_weight)
+            .collect::<Vec<_>>();
+        // Sum of the unit vectors from the estimate towards the other crabs.
+        let pull = euclidean_distance(&average, &estimate) * total_weight;
+        if pull <= coinciding {
+            converged = true;
+            break;
+        }
+        let kept = coinciding / pull;
+        let next = average
+            .iter()
+            .zip(&estimate)
+            .map(|(average, current)| (1.0 - kept) * average + kept * current)
+            .collect::<Vec<_>>();
+        let moved = euclidean_distance(&next, &estimate);
+        estimate = next;
+        if moved < WEISZFELD_TOLERANCE {
+            converged = true;
+            break;
+        }
+    }
+    if !converged {
+        return Err(format!(
+            "Weiszfeld iteration did not converge in {} steps",
+            WEISZFELD_MAX_ITERATIONS
+        ));
+    }
+
+    let distance = crabs
+        .iter()
+        .map(|crab| euclidean_distance(crab, &estimate))
+        .sum();
+    Ok(EuclideanAlignment { position: estimate, distance })
+}
+
+fn euclidean_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f64>()
+        .sqrt()
+}
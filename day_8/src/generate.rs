@@ -0,0 +1,52 @@
+use crate::signals::{SignalPattern, DIGITS};
+use crate::solver::{apply, Mask, Permutation};
+
+/// Scrambled puzzle line, with the patterns of the ten digits then the given output digits, for
+/// a display on which wire `w` lights segment `permutation[w]`.
+pub fn scrambled_line(permutation: &[usize], digits: &[usize]) -> Result<String, String> {
+    let not_a_permutation = || format!("{:?} is not a permutation of 7 wires", permutation);
+    if permutation.len() != 7 {
+        return Err(not_a_permutation());
+    }
+    let mut inverse = vec![usize::MAX; 7];
+    for (wire, segment) in permutation.iter().enumerate() {
+        match inverse.get_mut(*segment) {
+            Some(inverse_wire) if *inverse_wire == usize::MAX => *inverse_wire = wire,
+            _ => return Err(not_a_permutation()),
+        }
+    }
+
+    let scramble = |digit: usize| {
+        let segments = DIGITS
+            .get(digit)
+            .ok_or(format!("{} is not a digit", digit))?
+            .parse::<SignalPattern>()?
+            .mask();
+        Ok::<_, String>(wire_letters(apply(&inverse, segments)))
+    };
+    let patterns = (0..10).map(scramble).collect::<Result<Vec<_>, _>>()?;
+    let output = digits
+        .iter()
+        .map(|digit| scramble(*digit))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(format!("{} | {}", patterns.join(" "), output.join(" ")))
+}
+
+/// Parses a permutation written as the segment lit by each wire, e.g. `cfgabde` if wire a
+/// lights segment c, wire b lights segment f, etc.
+pub fn parse_permutation(permutation: &str) -> Result<Permutation, String> {
+    permutation
+        .chars()
+        .map(|segment| match segment {
+            'a'..='g' => Ok((segment as u8 - b'a') as usize),
+            _ => Err(format!("'{}' is not a valid segment value", segment)),
+        })
+        .collect()
+}
+
+fn wire_letters(wires: Mask) -> String {
+    (0..7)
+        .filter(|wire| wires & (1 << wire) != 0)
+        .map(|wire| (b'a' + wire as u8) as char)
+        .collect()
+}
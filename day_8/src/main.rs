@@ -1,32 +1,57 @@
-use crate::signals::{Segment, SignalPattern};
-use common::lazy_static::lazy_static;
-use common::regex::Regex;
-use std::collections::HashSet;
+use crate::generate::{parse_permutation, scrambled_line};
+use crate::render::render_side_by_side;
+use crate::signals::SignalPattern;
+use crate::solver::{solve, Alphabet, Mask, Permutation, Solution};
 use std::convert::TryInto;
 
+mod generate;
+mod render;
 mod signals;
+mod solver;
 
 static INPUT: &str = include_str!("input");
 
-/// Index = crab position, value = number of crabs with that position.
-/// E.g. if `crabs[2] == 5`, there are 5 crabs at position 2.
-type Crabs = [usize];
-
 fn main() -> Result<(), String> {
-    // let initial_positions = parse_input(INPUT)?;
-
-    let lines = parse_input(&INPUT)?;
+    let options = parse_args(std::env::args().skip(1))?;
+    let alphabet = options.alphabet;
+    let lines = parse_input(INPUT)?;
 
     let part_1_result = part_1(&lines);
     println!("Part 1 result: {}", part_1_result);
-    //
-    // let part_2_result = lowest_fuel_expenditure(&initial_positions, actual_fuel_cost)
-    //     .ok_or("Result 1 not found")?;
-    // println!("Part 2 result: {}", part_2_result);
+
+    let part_2_result = part_2(&lines, &alphabet)?;
+    println!("Part 2 result: {}", part_2_result);
+
+    if let Some(line_number) = options.render_line {
+        let line = line_number
+            .checked_sub(1)
+            .and_then(|index| lines.get(index))
+            .ok_or(format!("No line {}", line_number))?;
+        let patterns = line.patterns.iter().chain(&line.digits);
+        let patterns = patterns.map(|p| p.mask()).collect::<Vec<_>>();
+        if alphabet.segments() != 7 {
+            return Err("Only seven-segment displays can be rendered".to_owned());
+        }
+        let permutation = unique_wiring(&alphabet, line, line_number - 1)?;
+        print!("{}", render_side_by_side(&patterns, &permutation));
+    }
+
+    if let Some((permutation, digits)) = options.scramble {
+        println!("{}", scrambled_line(&permutation, &digits)?);
+    }
 
     Ok(())
 }
 
+/// Command line arguments: `[--glyphs FILE] [--render LINE] [--scramble PERMUTATION:DIGITS]`.
+struct Options {
+    alphabet: Alphabet,
+    /// Number (starting at 1) of the input line to render, scrambled and unscrambled.
+    render_line: Option<usize>,
+    /// Wiring and output digits of a scrambled line to generate.
+    scramble: Option<(Permutation, Vec<usize>)>,
+}
+
 fn part_1(lines: &[Line]) -> usize {
     lines.iter().fold(0, |acc, line| {
         acc + line
@@ -40,23 +65,91 @@ fn part_1(lines: &[Line]) -> usize {
     })
 }
 
-fn part_2(lines: &[Line]) -> Result<usize, String> {
-    let mut acc: usize = 0;
+/// `FILE` has one glyph per line, listing its lit segments (the standard seven-segment digits by
+/// default), and glyph values are their line index. `PERMUTATION` is the segment lit by each wire, e.g.
+/// `cfgabde`, and `DIGITS` the output digits, e.g. `1234`.
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut alphabet = Alphabet::seven_segment();
+    let (mut render_line, mut scramble) = (None, None);
+    while let Some(arg) = args.next() {
+        let value = args.next().ok_or(format!("Missing value for {}", arg))?;
+        match arg.as_str() {
+            "--glyphs" => {
+                let content = std::fs::read_to_string(&value)
+                    .map_err(|err| format!("Cannot read {}: {}", value, err))?;
+                alphabet = parse_glyphs(&content)?;
+            }
+            "--render" => {
+                let line = value
+                    .parse::<usize>()
+                    .map_err(|err| format!("Invalid line {}: {}", value, err))?;
+                render_line = Some(line);
+            }
+            "--scramble" => {
+                let (permutation, digits) = value
+                    .split_once(':')
+                    .ok_or(format!("Invalid scrambled line {}", value))?;
+                let digits = digits
+                    .chars()
+                    .map(|digit| digit.to_digit(10).map(|digit| digit as usize))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or(format!("Invalid digits {}", digits))?;
+                scramble = Some((parse_permutation(permutation)?, digits));
+            }
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    Ok(Options { alphabet, render_line, scramble })
+}
 
-    for line in lines {
-        let digit_1 = line.patterns.iter().find(|p| p.0.len() == 1).ok_or("Digit 1 not found")?;
-        let digit_4 = line.patterns.iter().find(|p| p.0.len() == 4).ok_or("Digit 4 not found")?;
-        let digit_7 = line.patterns.iter().find(|p| p.0.len() == 3).ok_or("Digit 7 not found")?;
-        let digit_8 = line.patterns.iter().find(|p| p.0.len() == 7).ok_or("Digit 8 not found")?;
-        let digits_6_9 = line.patterns.iter().filter(|p| p.0.len() == 6).collect::<Vec<_>>();
-        let digits
+/// Alphabet with one glyph per line. Its segments go up to the last segment letter used.
+fn parse_glyphs(input: &str) -> Result<Alphabet, String> {
+    let glyphs = input
+        .lines()
+        .map(|glyph| {
+            glyph
+                .trim()
+                .parse::<SignalPattern>()
+                .map(|glyph| glyph.mask())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let all_segments = glyphs.iter().fold(0, |all, glyph| all | glyph);
+    let segments = (Mask::BITS - all_segments.leading_zeros()) as usize;
+    Alphabet::new(segments, glyphs)
+}
 
+/// The only wiring consistent with the patterns of the line with the given index.
+fn unique_wiring(alphabet: &Alphabet, line: &Line, index: usize) -> Result<Permutation, String> {
+    let patterns = line.patterns.iter().map(|p| p.mask()).collect::<Vec<_>>();
+    match solve(alphabet, &patterns)? {
+        Solution::Unique(permutation) => Ok(permutation),
+        Solution::Ambiguous(_) => Err(format!("Several possible wirings for line {}", index + 1)),
+        Solution::Contradictory => Err(format!("No possible wiring for line {}", index + 1)),
     }
-
-    Ok(acc)
 }
 
+/// Sum of the output values, read in base `alphabet.len()` (base 10 for digits).
+fn part_2(lines: &[Line], alphabet: &Alphabet) -> Result<usize, String> {
+    let mut acc: usize = 0;
 
+    for (index, line) in lines.iter().enumerate() {
+        let permutation = unique_wiring(alphabet, line, index)?;
+        let value = line.digits.iter().try_fold(0usize, |value, digit| {
+            let digit = alphabet
+                .decode(&permutation, digit.mask())
+                .ok_or(format!("Invalid digit on line {}", index + 1))?;
+            value
+                .checked_mul(alphabet.len())
+                .and_then(|value| value.checked_add(digit))
+                .ok_or(format!("Value overflow on line {}", index + 1))
+        })?;
+        acc = acc
+            .checked_add(value)
+            .ok_or(format!("Sum overflow on line {}", index + 1))?;
+    }
+
+    Ok(acc)
+}
 
 fn parse_input(input: &str) -> Result<Vec<Line>, String> {
     input.lines().map(parse_line).collect()
@@ -89,12 +182,182 @@ struct Line {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::render::render_digit;
+    use crate::solver::{all_solutions, apply};
 
-    // static TEST_CRABS: [usize; 10] = [16, 1, 2, 0, 4, 2, 7, 1, 2, 14];
+    static TEST_INPUT: &str = "\
+be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcbe
+edbfga begcd cbg gc gcadebf fbgde acbgfd abcde gfcbed gfec | fcgedb cgb dgebacf gc
+fgaebd cg bdaec gdafb agbcfd gdcbef bgcad gfac gcb cdgabef | cg cg fdcagb cbg
+fbegcd cbd adcefb dageb afcb bc aefdc ecdab fgdeca fcdbega | efabcd cedba gadfec cb
+aecbfdg fbg gf bafeg dbefa fcge gcbea fcaegb dgceab fcbdga | gecf egdcabf bgf bfgea
+fgeab ca afcebg bdacfeg cfaedg gcfdb baec bfadeg bafgc acf | gebdcfa ecba ca fadegcb
+dbcfg fgd bdegcaf fgec aegbdf ecdfab fbedc dacgb gdcebf gf | cefg dcbef fcge gbcadfe
+bdfegc cbegaf gecbf dfcage bdacg ed bedf ced adcbefg gebcd | ed bcgafe cdgba cbgef
+egadfb cdbfeg cegd fecab cgb gbdefca cg fgcdab egfdb bfceg | gbdfcae bgc cg cgb
+gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce";
 
     #[test]
-    fn test_part_1() {}
+    fn test_part_1() {
+        let lines = parse_input(TEST_INPUT).unwrap();
+        assert_eq!(26, part_1(&lines));
+    }
 
     #[test]
-    fn test_part_2() {}
+    fn test_part_2() {
+        let line = parse_line(
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        )
+        .unwrap();
+        let patterns = line.patterns.iter().map(|p| p.mask()).collect::<Vec<_>>();
+        // Wire d is segment a, wire e is segment b, wire a is segment c, etc.
+        let expected = Solution::Unique(vec![2, 5, 6, 0, 1, 3, 4]);
+        assert_eq!(Ok(expected), solve(&Alphabet::seven_segment(), &patterns));
+        assert_eq!(Ok(5353), part_2(&[line], &Alphabet::seven_segment()));
+
+        let lines = parse_input(TEST_INPUT).unwrap();
+        assert_eq!(Ok(61229), part_2(&lines, &Alphabet::seven_segment()));
+
+        let invalid = parse_line("ab abc abcd abcdefg a b c d e f | ab ab ab ab").unwrap();
+        assert!(part_2(&[invalid], &Alphabet::seven_segment()).is_err());
+    }
+
+    #[test]
+    fn test_solver() {
+        // Swapping segments 0 and 2 shows the same glyphs.
+        let symmetric = Alphabet::new(3, vec![0b011, 0b110, 0b010]).unwrap();
+        let expected = Solution::Ambiguous(vec![vec![0, 1, 2], vec![2, 1, 0]]);
+        assert_eq!(Ok(expected), solve(&symmetric, &[0b011, 0b110, 0b010]));
+        assert_eq!(
+            Ok(Solution::Contradictory),
+            solve(&symmetric, &[0b011, 0b110, 0b101])
+        );
+
+        let seven_segment = Alphabet::seven_segment();
+        assert_eq!(Ok(Solution::Contradictory), solve(&seven_segment, &[0b1]));
+        // Only digits 1 and 7: wires 2 to 6 can be any of the other segments.
+        let solutions = all_solutions(&seven_segment, &[0b011, 0b111]).unwrap();
+        assert_eq!(2 * 24, solutions.len());
+        assert!(solve(&seven_segment, &[1 << 7]).is_err());
+        // The search stops at the second wiring, out of 12 * 11! for this single pattern.
+        let twelve_segment = Alphabet::new(12, vec![0b0111_1111_1111, 0b1111_1111_1110]).unwrap();
+        match solve(&twelve_segment, &[0b0111_1111_1111]) {
+            Ok(Solution::Ambiguous(permutations)) => assert_eq!(2, permutations.len()),
+            other => panic!("Expected an ambiguous wiring, got {:?}", other),
+        }
+
+        assert!(Alphabet::new(3, vec![0b1000]).is_err());
+        assert!(Alphabet::new(3, vec![0b1, 0b1]).is_err());
+        assert!(Alphabet::new(65, vec![]).is_err());
+        assert!(Alphabet::new(64, vec![1 << 63, Mask::MAX]).is_ok());
+        assert!(Alphabet::new(63, vec![1 << 63]).is_err());
+    }
+
+    #[test]
+    fn test_part_2_custom_alphabet() {
+        let glyphs = "a\nab\nabc\nabcd\nabcde\nabcdef\nabcdefg\nabcdefgh\nh\ngh\nfgh\nefgh\n";
+        let alphabet = parse_glyphs(glyphs).unwrap();
+        assert_eq!((8, 12), (alphabet.segments(), alphabet.len()));
+        let patterns = glyphs.lines().collect::<Vec<_>>().join(" ");
+        let line = parse_line(&format!("{} | h abcdefgh ab a", patterns)).unwrap();
+        // Glyphs 8, 7, 1 and 0, in base 12.
+        assert_eq!(Ok(8 * 1728 + 7 * 144 + 12), part_2(&[line], &alphabet));
+
+        assert_eq!(52, parse_glyphs("a\nZ").unwrap().segments());
+        assert!(parse_glyphs("a\n1").is_err());
+    }
+
+    #[test]
+    fn test_solver_custom_alphabet() {
+        let mut next = common::random_numbers(8);
+        for _ in 0..20 {
+            // A fourteen-segment display with random glyphs, scrambled by a random permutation.
+            let mut glyphs: Vec<Mask> = Vec::new();
+            while glyphs.len() < 12 {
+                let glyph = next(1 << 14);
+                if !glyphs.contains(&glyph) {
+                    glyphs.push(glyph);
+                }
+            }
+            let alphabet = Alphabet::new(14, glyphs.clone()).unwrap();
+            let mut permutation = (0..14).collect::<Vec<usize>>();
+            for i in (1..14).rev() {
+                permutation.swap(i, next(i as u64 + 1) as usize);
+            }
+            let mut inverse = vec![0; 14];
+            for (wire, segment) in permutation.iter().enumerate() {
+                inverse[*segment] = wire;
+            }
+            let patterns = glyphs
+                .iter()
+                .map(|glyph| apply(&inverse, *glyph))
+                .collect::<Vec<_>>();
+
+            let solutions = all_solutions(&alphabet, &patterns).unwrap();
+            assert!(solutions.contains(&permutation));
+            for solution in solutions {
+                for pattern in &patterns {
+                    assert!(alphabet.decode(&solution, *pattern).is_some());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let line = parse_line(
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf",
+        )
+        .unwrap();
+        let permutation = unique_wiring(&Alphabet::seven_segment(), &line, 0).unwrap();
+        let one = line.patterns[9].mask();
+        let expected = [
+            " aaaa   |   .... ",
+            "b    .  |  .    c",
+            "b    .  |  .    c",
+            " ....   |   .... ",
+            ".    .  |  .    f",
+            ".    .  |  .    f",
+            " ....   |   .... ",
+        ];
+        let rendered = render_side_by_side(&[one], &permutation);
+        assert_eq!(expected.to_vec(), rendered.lines().collect::<Vec<_>>());
+        assert_eq!(
+            vec![" aaaa ", "b    c", "b    c", " dddd ", "e    f", "e    f", " gggg "],
+            render_digit(0b1111111)
+        );
+    }
+
+    #[test]
+    fn test_scrambled_lines() {
+        let line = scrambled_line(&parse_permutation("cfgabde").unwrap(), &[1, 2, 3, 4]).unwrap();
+        assert_eq!(
+            "abcdeg ab acdfg abcdf abef bcdef bcdefg abd abcdefg abcdef | ab acdfg abcdf abef",
+            line
+        );
+        assert_eq!(
+            Ok(1234),
+            part_2(&[parse_line(&line).unwrap()], &Alphabet::seven_segment())
+        );
+
+        // Property test: decoding random displays gives back their digits.
+        let mut next = common::random_numbers(39);
+        for _ in 0..2000 {
+            let mut permutation = (0..7).collect::<Vec<usize>>();
+            for i in (1..7).rev() {
+                permutation.swap(i, next(i as u64 + 1) as usize);
+            }
+            let digits = (0..4).map(|_| next(10) as usize).collect::<Vec<_>>();
+            let line = parse_line(&scrambled_line(&permutation, &digits).unwrap()).unwrap();
+            let expected = digits.iter().fold(0, |value, digit| value * 10 + digit);
+            let alphabet = Alphabet::seven_segment();
+            assert_eq!(Ok(expected), part_2(&[line], &alphabet));
+        }
+
+        assert!(scrambled_line(&[0, 1, 2, 3, 4, 5], &[1]).is_err());
+        assert!(scrambled_line(&[0, 1, 2, 3, 4, 5, 5], &[1]).is_err());
+        assert!(scrambled_line(&[0, 1, 2, 3, 4, 5, 7], &[1]).is_err());
+        assert!(scrambled_line(&[0, 1, 2, 3, 4, 5, 6], &[10]).is_err());
+        assert!(parse_permutation("abcdefh").is_err());
+    }
 }
@@ -0,0 +1,53 @@
+use crate::solver::{apply, Mask};
+
+/// Rows of a seven-segment digit, with the letter of each lit segment and `.` for the others, as
+/// in the puzzle description.
+pub fn render_digit(segments: Mask) -> Vec<String> {
+    let lit = |segment: usize| {
+        if segments & (1 << segment) != 0 {
+            (b'a' + segment as u8) as char
+        } else {
+            '.'
+        }
+    };
+    let horizontal = |segment| format!(" {} ", lit(segment).to_string().repeat(4));
+    let vertical = |left, right| format!("{}    {}", lit(left), lit(right));
+    vec![
+        horizontal(0),
+        vertical(1, 2),
+        vertical(1, 2),
+        horizontal(3),
+        vertical(4, 5),
+        vertical(4, 5),
+        horizontal(6),
+    ]
+}
+
+/// Renders the patterns as if their wires were correctly connected, then, separated by `|`, the
+/// digits they actually show with the given wiring.
+pub fn render_side_by_side(patterns: &[Mask], permutation: &[usize]) -> String {
+    let scrambled = patterns
+        .iter()
+        .map(|pattern| render_digit(*pattern))
+        .collect::<Vec<_>>();
+    let unscrambled = patterns
+        .iter()
+        .map(|pattern| render_digit(apply(permutation, *pattern)))
+        .collect::<Vec<_>>();
+    let row_of = |digits: &[Vec<String>], row: usize| {
+        digits
+            .iter()
+            .map(|digit| digit[row].as_str())
+            .collect::<Vec<_>>()
+            .join("  ")
+    };
+    (0..7)
+        .map(|row| {
+            format!(
+                "{}  |  {}\n",
+                row_of(&scrambled, row),
+                row_of(&unscrambled, row)
+            )
+        })
+        .collect()
+}
@@ -1,37 +1,42 @@
+use crate::solver::Mask;
 use std::collections::HashSet;
 use std::convert::TryFrom;
 use std::str::FromStr;
 
-#[derive(PartialEq, Eq, Hash)]
-pub enum Segment {
-    A,
-    B,
-    C,
-    D,
-    E,
-    F,
-    G,
-}
+/// Segment (or wire) of a display, named `a` to `z` then `A` to `Z`: the puzzle displays use
+/// `a` to `g`, other displays may have more segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Segment(pub usize);
+
+/// Segments lit for each digit, on a correctly wired display.
+pub static DIGITS: [&str; 10] = [
+    "abcefg", "cf", "acdeg", "acdfg", "bcdf", "abdfg", "abdefg", "acf", "abcdefg", "abcdfg",
+];
 
 impl TryFrom<char> for Segment {
     type Error = String;
 
     fn try_from(value: char) -> Result<Self, Self::Error> {
         match value {
-            'a' => Ok(Segment::A),
-            'b' => Ok(Segment::B),
-            'c' => Ok(Segment::C),
-            'd' => Ok(Segment::D),
-            'e' => Ok(Segment::E),
-            'f' => Ok(Segment::F),
-            'g' => Ok(Segment::G),
+            'a'..='z' => Ok(Segment((value as u8 - b'a') as usize)),
+            'A'..='Z' => Ok(Segment((value as u8 - b'A') as usize + 26)),
             _ => Err(format!("'{}' is not a valid segment value", value)),
         }
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct SignalPattern(pub HashSet<Segment>);
 
+impl SignalPattern {
+    /// Bit mask of the lit segments, with segment `a` as the least significant bit.
+    pub fn mask(&self) -> Mask {
+        self.0
+            .iter()
+            .fold(0, |mask, segment| mask | (1 << segment.0))
+    }
+}
+
 impl FromStr for SignalPattern {
     type Err = String;
 
@@ -0,0 +1,231 @@
+use crate::signals::{SignalPattern, DIGITS};
+
+/// Set of wires or segments, as a bit mask: bit `i` is set if wire or segment `i` is lit.
+pub type Mask = u64;
+
+/// Wire → segment mapping: `permutation[wire] == segment`.
+pub type Permutation = Vec<usize>;
+
+/// Glyphs which a display can show, e.g. the ten digits of a seven-segment display.
+#[derive(Debug, Clone)]
+pub struct Alphabet {
+    segments: usize,
+    glyphs: Vec<Mask>,
+}
+
+impl Alphabet {
+    pub fn new(segments: usize, glyphs: Vec<Mask>) -> Result<Alphabet, String> {
+        if segments > Mask::BITS as usize {
+            return Err(format!("At most {} segments are supported", Mask::BITS));
+        }
+        if let Some(glyph) = glyphs
+            .iter()
+            .find(|glyph| glyph.checked_shr(segments as u32).unwrap_or(0) != 0)
+        {
+            return Err(format!(
+                "Glyph {:b} has more than {} segments",
+                glyph, segments
+            ));
+        }
+        for (index, glyph) in glyphs.iter().enumerate() {
+            if glyphs[..index].contains(glyph) {
+                return Err(format!("Duplicate glyph {:b}", glyph));
+            }
+        }
+        Ok(Alphabet { segments, glyphs })
+    }
+
+    /// The digits 0 to 9 of a seven-segment display.
+    pub fn seven_segment() -> Alphabet {
+        let glyphs = DIGITS
+            .iter()
+            .map(|digit| digit.parse::<SignalPattern>().unwrap().mask())
+            .collect();
+        Alphabet { segments: 7, glyphs }
+    }
+
+    pub fn segments(&self) -> usize {
+        self.segments
+    }
+
+    /// Number of glyphs.
+    pub fn len(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    /// Index of the glyph shown by the given scrambled wires, if any.
+    pub fn decode(&self, permutation: &[usize], wires: Mask) -> Option<usize> {
+        let segments = apply(permutation, wires);
+        self.glyphs.iter().position(|glyph| *glyph == segments)
+    }
+
+    fn all_segments(&self) -> Mask {
+        if self.segments == Mask::BITS as usize {
+            Mask::MAX
+        } else {
+            (1 << self.segments) - 1
+        }
+    }
+}
+
+/// Segments lit by the given wires.
+pub fn apply(permutation: &[usize], wires: Mask) -> Mask {
+    permutation
+        .iter()
+        .enumerate()
+        .filter(|(wire, _)| wires & (1 << wire) != 0)
+        .fold(0, |segments, (_, segment)| segments | (1 << segment))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Solution {
+    /// Exactly one wiring is consistent with the patterns.
+    Unique(Permutation),
+    /// Several wirings are consistent with the patterns (only the first two found are listed).
+    Ambiguous(Vec<Permutation>),
+    /// No wiring maps every pattern to a glyph.
+    Contradictory,
+}
+
+/// Finds the wirings for which every scrambled pattern shows a glyph of the alphabet, stopping as
+/// soon as a second one shows that the wiring is ambiguous.
+pub fn solve(alphabet: &Alphabet, patterns: &[Mask]) -> Result<Solution, String> {
+    let mut permutations = solutions(alphabet, patterns, Some(2))?;
+    Ok(match permutations.len() {
+        0 => Solution::Contradictory,
+        1 => Solution::Unique(permutations.remove(0)),
+        _ => Solution::Ambiguous(permutations),
+    })
+}
+
+/// Every wiring for which every scrambled pattern shows a glyph of the alphabet.
+#[cfg(test)]
+pub fn all_solutions(alphabet: &Alphabet, patterns: &[Mask]) -> Result<Vec<Permutation>, String> {
+    solutions(alphabet, patterns, None)
+}
+
+/// Wirings for which every scrambled pattern shows a glyph of the alphabet, at most `limit` of
+/// them.
+///
+/// Each wire starts with all the segments as candidates. Constraints are propagated until
+/// nothing changes: a pattern can only show a glyph with as many segments, which is compatible
+/// with the candidates of its wires, and a segment already assigned to a wire cannot be
+/// assigned to another one. The search then branches on the wire with the fewest candidates.
+fn solutions(
+    alphabet: &Alphabet,
+    patterns: &[Mask],
+    limit: Option<usize>,
+) -> Result<Vec<Permutation>, String> {
+    let all_segments = alphabet.all_segments();
+    if let Some(pattern) = patterns
+        .iter()
+        .find(|pattern| **pattern & !all_segments != 0)
+    {
+        return Err(format!(
+            "Pattern {:b} has more than {} wires",
+            pattern, alphabet.segments
+        ));
+    }
+    let mut solutions = Vec::new();
+    let candidates = vec![all_segments; alphabet.segments];
+    search(alphabet, patterns, candidates, limit, &mut solutions);
+    Ok(solutions)
+}
+
+fn search(
+    alphabet: &Alphabet,
+    patterns: &[Mask],
+    candidates: Vec<Mask>,
+    limit: Option<usize>,
+    solutions: &mut Vec<Permutation>,
+) {
+    if limit == Some(solutions.len()) {
+        return;
+    }
+    let candidates = match propagate(alphabet, patterns, candidates) {
+        Some(candidates) => candidates,
+        None => return,
+    };
+    let undecided = (0..candidates.len())
+        .filter(|wire| candidates[*wire].count_ones() > 1)
+        .min_by_key(|wire| candidates[*wire].count_ones());
+    match undecided {
+        None => {
+            let permutation = candidates
+                .iter()
+                .map(|segments| segments.trailing_zeros() as usize)
+                .collect::<Vec<_>>();
+            let shows_glyphs = patterns
+                .iter()
+                .all(|pattern| alphabet.decode(&permutation, *pattern).is_some());
+            if shows_glyphs {
+                solutions.push(permutation);
+            }
+        }
+        Some(wire) => {
+            for segment in 0..alphabet.segments {
+                if candidates[wire] & (1 << segment) != 0 {
+                    let mut next = candidates.clone();
+                    next[wire] = 1 << segment;
+                    search(alphabet, patterns, next, limit, solutions);
+                }
+            }
+        }
+    }
+}
+
+/// Narrows the candidate segments of each wire, or returns `None` if a wire has no candidate
+/// left.
+fn propagate(
+    alphabet: &Alphabet,
+    patterns: &[Mask],
+    mut candidates: Vec<Mask>,
+) -> Option<Vec<Mask>> {
+    let all_segments = alphabet.all_segments();
+    loop {
+        let before = candidates.clone();
+
+        for pattern in patterns {
+            let compatible = |glyph: &&Mask| {
+                glyph.count_ones() == pattern.count_ones()
+                    && candidates.iter().enumerate().all(|(wire, segments)| {
+                        if pattern & (1 << wire) != 0 {
+                            segments & **glyph != 0
+                        } else {
+                            segments & !**glyph & all_segments != 0
+                        }
+                    })
+            };
+            let (mut inside, mut outside) = (0, 0);
+            for glyph in alphabet.glyphs.iter().filter(compatible) {
+                inside |= glyph;
+                outside |= !glyph & all_segments;
+            }
+            for (wire, segments) in candidates.iter_mut().enumerate() {
+                *segments &= if pattern & (1 << wire) != 0 {
+                    inside
+                } else {
+                    outside
+                };
+            }
+        }
+
+        for wire in 0..candidates.len() {
+            if candidates[wire].count_ones() == 1 {
+                let segment = candidates[wire];
+                for (other, segments) in candidates.iter_mut().enumerate() {
+                    if other != wire {
+                        *segments &= !segment;
+                    }
+                }
+            }
+        }
+
+        if candidates.contains(&0) {
+            return None;
+        }
+        if candidates == before {
+            return Some(candidates);
+        }
+    }
+}
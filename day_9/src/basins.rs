@@ -0,0 +1,65 @@
+use crate::HeightsMap;
+use std::collections::VecDeque;
+
+/// Area delimited by walls (cells of height 9, by default).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Basin {
+    /// Lowest cell of the basin (the first one in row order, if several have the same height).
+    pub low_point: (usize, usize),
+    /// All the cells of the basin, in row order.
+    pub cells: Vec<(usize, usize)>,
+}
+
+impl Basin {
+    pub fn area(&self) -> usize {
+        self.cells.len()
+    }
+}
+
+/// All the basins of a map, largest first.
+pub struct Basins(Vec<Basin>);
+
+impl Basins {
+    pub fn find(heights_map: &HeightsMap) -> Basins {
+        let mut visited = vec![vec![false; heights_map.y_max + 1]; heights_map.x_max + 1];
+        let mut basins = Vec::new();
+        let mut fill_queue: VecDeque<(usize, usize)> = VecDeque::new();
+        for x_start in 0..=heights_map.x_max {
+            for y_start in 0..=heights_map.y_max {
+                if visited[x_start][y_start] || heights_map.is_wall(x_start, y_start) {
+                    continue;
+                }
+                let mut cells = Vec::new();
+                fill_queue.push_back((x_start, y_start));
+                visited[x_start][y_start] = true;
+                while let Some((next_x, next_y)) = fill_queue.pop_front() {
+                    for (x, y) in heights_map.neighbor_points(next_x, next_y) {
+                        if !visited[x][y] && !heights_map.is_wall(x, y) {
+                            fill_queue.push_back((x, y));
+                            visited[x][y] = true;
+                        }
+                    }
+                    cells.push((next_x, next_y));
+                }
+                cells.sort_unstable();
+                let low_point = *cells
+                    .iter()
+                    .min_by_key(|(x, y)| heights_map.height(*x, *y))
+                    .unwrap();
+                basins.push(Basin { low_point, cells });
+            }
+        }
+        // Stable sort: basins with the same area stay in the order of their first cell.
+        basins.sort_by_key(|basin| std::cmp::Reverse(basin.area()));
+        Basins(basins)
+    }
+
+    pub fn all(&self) -> &[Basin] {
+        &self.0
+    }
+
+    /// The `k` largest basins (or all of them, if there are less than `k`), largest first.
+    pub fn top_k(&self, k: usize) -> &[Basin] {
+        &self.0[..k.min(self.0.len())]
+    }
+}
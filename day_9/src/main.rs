@@ -1,9 +1,23 @@
-use std::collections::{BinaryHeap, VecDeque};
+use crate::basins::Basins;
+use crate::render::{render, ColorMode};
+use crate::segmentation::Segmentation;
+use crate::watershed::Watershed;
+use std::str::FromStr;
+
+mod basins;
+mod render;
+mod segmentation;
+mod union_find;
+mod watershed;
 
 static INPUT: &str = include_str!("input");
 
 fn main() -> Result<(), String> {
-    let heights_map = parse_input(INPUT)?;
+    let options = parse_args(std::env::args().skip(1))?;
+    let mut heights_map = parse_input(INPUT)?.with_connectivity(options.connectivity);
+    if let Some(wall_height) = options.wall_height {
+        heights_map = heights_map.with_wall_height(wall_height);
+    }
 
     let part_1_result = part_1(&heights_map);
     println!("Part 1 result: {}", part_1_result);
@@ -11,9 +25,104 @@ fn main() -> Result<(), String> {
     let part_2_result = part_2(&heights_map);
     println!("Part 2 result: {}", part_2_result);
 
+    let basins = Basins::find(&heights_map);
+    println!("Number of basins: {}", basins.all().len());
+    for basin in basins.top_k(3) {
+        println!(
+            "Basin with low point {:?}: area {}",
+            basin.low_point,
+            basin.area()
+        );
+    }
+
+    let segmentation = Segmentation::new(&heights_map);
+    let flow_areas = segmentation.areas();
+    println!(
+        "Number of basins by flow: {}, largest area: {}",
+        segmentation.len(),
+        flow_areas.iter().max().unwrap_or(&0)
+    );
+    for (x, y) in options.drain_cells {
+        if x > heights_map.x_max || y > heights_map.y_max {
+            return Err(format!("Cell {},{} is outside of the map", x, y));
+        }
+        match segmentation.drains_into(x, y) {
+            Some(low_point) => println!("Cell {},{} drains into {:?}", x, y, low_point),
+            None => println!("Cell {},{} is a wall", x, y),
+        }
+    }
+
+    if let Some(color_mode) = options.render {
+        print!("{}", render(&heights_map, color_mode));
+    }
+
+    if options.watershed {
+        let watershed = Watershed::new(&heights_map);
+        print!("{}", watershed.to_char_map());
+        let (x, y) = (0..=heights_map.x_max)
+            .flat_map(|x| (0..=heights_map.y_max).map(move |y| (x, y)))
+            .max_by_key(|(x, y)| watershed.accumulation[*x][*y])
+            .unwrap();
+        println!(
+            "Largest flow accumulation: {} cells through {},{}",
+            watershed.accumulation[x][y], x, y
+        );
+        for saddle in &watershed.saddles {
+            println!(
+                "Basins {} and {} merge at height {} through {:?}",
+                saddle.basins.0, saddle.basins.1, saddle.spill_height, saddle.cell
+            );
+        }
+    }
+
     Ok(())
 }
 
+/// Command line arguments: `[--connectivity 4|8|hex] [--wall-height H] [--drain X,Y]...
+/// [--watershed] [--render auto|color|plain]`.
+struct Options {
+    connectivity: Connectivity,
+    /// Minimum height of the walls between basins, 9 if not specified.
+    wall_height: Option<usize>,
+    /// Cells for which to show where they drain into.
+    drain_cells: Vec<(usize, usize)>,
+    /// Whether to show the flow directions, ridges and saddles.
+    watershed: bool,
+    /// How to render the map, if it should be.
+    render: Option<ColorMode>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let (mut connectivity, mut wall_height) = (Connectivity::Four, None);
+    let (mut drain_cells, mut watershed, mut render) = (Vec::new(), false, None);
+    while let Some(arg) = args.next() {
+        if arg == "--watershed" {
+            watershed = true;
+            continue;
+        }
+        let value = args.next().ok_or(format!("Missing value for {}", arg))?;
+        match arg.as_str() {
+            "--connectivity" => connectivity = value.parse()?,
+            "--render" => render = Some(value.parse()?),
+            "--wall-height" => {
+                let height = value
+                    .parse::<usize>()
+                    .map_err(|err| format!("Invalid height {}: {}", value, err))?;
+                wall_height = Some(height);
+            }
+            "--drain" => {
+                let cell = value
+                    .split_once(',')
+                    .and_then(|(x, y)| Some((x.parse().ok()?, y.parse().ok()?)))
+                    .ok_or(format!("Invalid cell {}", value))?;
+                drain_cells.push(cell);
+            }
+            _ => return Err(format!("Unknown option {}", arg)),
+        }
+    }
+    Ok(Options { connectivity, wall_height, drain_cells, watershed, render })
+}
+
 fn parse_input(input: &str) -> Result<HeightsMap, String> {
     let heights = input
         .lines()
@@ -34,7 +143,7 @@ fn part_1(heights_map: &HeightsMap) -> usize {
     let mut risk_levels_sum: usize = 0;
     for x in 0..=heights_map.x_max {
         for y in 0..=heights_map.y_max {
-            for risk_level in heights_map.low_point_risk_level(x, y) {
+            if let Some(risk_level) = heights_map.low_point_risk_level(x, y) {
                 risk_levels_sum += risk_level;
             }
         }
@@ -43,42 +152,34 @@ fn part_1(heights_map: &HeightsMap) -> usize {
 }
 
 fn part_2(heights_map: &HeightsMap) -> usize {
-    let mut filled_heights_map = heights_map.heights.clone();
-    let mut basin_areas = BinaryHeap::new(); //Max heap
-    let mut fill_queue: VecDeque<(usize, usize)> = VecDeque::new();
-    for x_start in 0..=heights_map.x_max {
-        for y_start in 0..=heights_map.y_max {
-            if filled_heights_map[x_start][y_start] < 9 {
-                let mut area: usize = 0;
-                fill_queue.push_back((x_start, y_start));
-                filled_heights_map[x_start][y_start] = 9;
-
-                while let Some((next_x, next_y)) = fill_queue.pop_front() {
-                    for (x, y) in heights_map.neighbor_points(next_x, next_y) {
-                        if filled_heights_map[x][y] < 9 {
-                            fill_queue.push_back((x, y));
-                            filled_heights_map[x][y] = 9;
-                        }
-                    }
-                    area += 1;
-                }
-
-                basin_areas.push(area);
-            }
-        }
-    }
-    basin_areas.iter().take(3).fold(1, |acc, next| acc * next)
+    Basins::find(heights_map)
+        .top_k(3)
+        .iter()
+        .map(|basin| basin.area())
+        .product()
 }
 
-struct Coord {
-    x: usize,
-    y: usize,
+/// Cells considered adjacent to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Connectivity {
+    /// Up, down, left and right.
+    Four,
+    /// Also the diagonals.
+    Eight,
+    /// Hexagonal cells, with odd rows shifted half a cell to the right.
+    Hex,
 }
 
-impl Coord {
-    fn is_neighbor(&self, other: &Coord) -> bool {
-        (self.x == other.x && (self.y == other.y - 1 || self.y == other.y + 1))
-            || (self.y == other.y && (self.x == other.x - 1 || self.x == other.x + 1))
+impl FromStr for Connectivity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "4" => Ok(Connectivity::Four),
+            "8" => Ok(Connectivity::Eight),
+            "hex" => Ok(Connectivity::Hex),
+            _ => Err(format!("Unknown connectivity {}", s)),
+        }
     }
 }
 
@@ -86,6 +187,10 @@ struct HeightsMap {
     heights: Vec<Vec<usize>>,
     x_max: usize,
     y_max: usize,
+    connectivity: Connectivity,
+    /// Whether a cell with the given height is a wall, which is never a low point and doesn't
+    /// belong to any basin.
+    wall: Box<dyn Fn(usize) -> bool>,
 }
 
 impl HeightsMap {
@@ -97,46 +202,88 @@ impl HeightsMap {
             let y_len = heights[0].len();
             if y_len == 0 {
                 Err("HeightsMap with empty y dimension".to_owned())
-            } else if heights.iter().find(|line| line.len() != y_len).is_some() {
+            } else if heights.iter().any(|line| line.len() != y_len) {
                 Err("HeightsMap with inconsistent y dimensions".to_owned())
             } else {
-                Ok(HeightsMap { heights, x_max: x_len - 1, y_max: y_len - 1 })
+                Ok(HeightsMap {
+                    heights,
+                    x_max: x_len - 1,
+                    y_max: y_len - 1,
+                    connectivity: Connectivity::Four,
+                    wall: Box::new(|height| height >= 9),
+                })
             }
         }
     }
+    fn with_connectivity(mut self, connectivity: Connectivity) -> HeightsMap {
+        self.connectivity = connectivity;
+        self
+    }
+    fn with_wall(mut self, wall: impl Fn(usize) -> bool + 'static) -> HeightsMap {
+        self.wall = Box::new(wall);
+        self
+    }
+    /// Cells at least as high as `wall_height` are walls (9 by default).
+    fn with_wall_height(self, wall_height: usize) -> HeightsMap {
+        self.with_wall(move |height| height >= wall_height)
+    }
     fn height(&self, x: usize, y: usize) -> usize {
         self.heights[x][y]
     }
+    fn is_wall(&self, x: usize, y: usize) -> bool {
+        (self.wall)(self.height(x, y))
+    }
     fn neighbor_points(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
-        let mut neighbors: Vec<(usize, usize)> = Vec::new();
-        if x > 0 {
-            neighbors.push((x - 1, y));
-        }
-        if x < self.x_max {
-            neighbors.push((x + 1, y))
-        }
-        if y > 0 {
-            neighbors.push((x, y - 1));
-        }
-        if y < self.y_max {
-            neighbors.push((x, y + 1))
+        let offsets: &[(isize, isize)] = match self.connectivity {
+            Connectivity::Four => &[(-1, 0), (1, 0), (0, -1), (0, 1)],
+            Connectivity::Eight => &[
+                (-1, -1),
+                (-1, 0),
+                (-1, 1),
+                (0, -1),
+                (0, 1),
+                (1, -1),
+                (1, 0),
+                (1, 1),
+            ],
+            Connectivity::Hex if x.is_multiple_of(2) => {
+                &[(-1, -1), (-1, 0), (0, -1), (0, 1), (1, -1), (1, 0)]
+            }
+            Connectivity::Hex => &[(-1, 0), (-1, 1), (0, -1), (0, 1), (1, 0), (1, 1)],
+        };
+        offsets
+            .iter()
+            .filter_map(|(d_x, d_y)| {
+                let x = x.checked_add_signed(*d_x).filter(|x| *x <= self.x_max)?;
+                let y = y.checked_add_signed(*d_y).filter(|y| *y <= self.y_max)?;
+                Some((x, y))
+            })
+            .collect()
+    }
+    /// Lowest neighbor which is not a wall and is strictly lower than the cell (the first one in
+    /// neighbor order, in case of a tie), or `None` if the cell is a wall or a sink.
+    fn steepest_descent(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        if self.is_wall(x, y) {
+            return None;
         }
-        neighbors
+        self.neighbor_points(x, y)
+            .into_iter()
+            .filter(|(x, y)| !self.is_wall(*x, *y))
+            .min_by_key(|(x, y)| self.height(*x, *y))
+            .filter(|(n_x, n_y)| self.height(*n_x, *n_y) < self.height(x, y))
     }
+    /// Heights of the neighbors which are not walls.
     fn neighbor_heights(&self, x: usize, y: usize) -> Vec<usize> {
         self.neighbor_points(x, y)
             .iter()
+            .filter(|(x, y)| !self.is_wall(*x, *y))
             .map(|(x, y)| self.height(*x, *y))
             .collect()
     }
     fn low_point_risk_level(&self, x: usize, y: usize) -> Option<usize> {
         let height = self.height(x, y);
         let neighbors = self.neighbor_heights(x, y);
-        if neighbors
-            .iter()
-            .find(|neighbor| **neighbor <= height)
-            .is_some()
-        {
+        if self.is_wall(x, y) || neighbors.iter().any(|neighbor| *neighbor <= height) {
             None
         } else {
             Some(height + 1)
@@ -147,6 +294,8 @@ impl HeightsMap {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::render::{render, ColorMode};
+    use crate::watershed::Saddle;
 
     fn test_map() -> HeightsMap {
         HeightsMap::new(vec![
@@ -172,4 +321,186 @@ mod tests {
         let expected: usize = 1134;
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_basins() {
+        let basins = Basins::find(&test_map());
+        let areas = basins.all().iter().map(|b| b.area()).collect::<Vec<_>>();
+        assert_eq!(vec![14, 9, 9, 3], areas);
+        let low_points = basins.all().iter().map(|b| b.low_point).collect::<Vec<_>>();
+        assert_eq!(vec![(2, 2), (0, 9), (4, 6), (0, 1)], low_points);
+        assert_eq!(vec![(0, 0), (0, 1), (1, 0)], basins.all()[3].cells);
+        assert_eq!(2, basins.top_k(2).len());
+        assert_eq!(4, basins.top_k(10).len());
+    }
+
+    #[test]
+    fn test_basins_not_in_heap_order() {
+        // Basins are found with areas 3, 2, 1 then 10: a max-heap of these areas is stored as
+        // [10, 3, 1, 2], so its first three elements are not the three largest areas.
+        let heights_map = parse_input("0129019099\n9999999999\n1234567878").unwrap();
+        let areas = Basins::find(&heights_map)
+            .all()
+            .iter()
+            .map(|basin| basin.area())
+            .collect::<Vec<_>>();
+        assert_eq!(vec![10, 3, 2, 1], areas);
+        assert_eq!(10 * 3 * 2, part_2(&heights_map));
+
+        let heights_map = parse_input("1919191\n9999999\n2192939").unwrap();
+        assert_eq!(2, part_2(&heights_map));
+        let heights_map = parse_input("99\n99").unwrap();
+        assert!(Basins::find(&heights_map).top_k(3).is_empty());
+    }
+
+    #[test]
+    fn test_segmentation() {
+        let heights_map = test_map();
+        let segmentation = Segmentation::new(&heights_map);
+        assert_eq!(vec![3, 9, 14, 9], segmentation.areas());
+        assert_eq!(Some((0, 1)), segmentation.drains_into(1, 0));
+        assert_eq!(Some((2, 2)), segmentation.drains_into(3, 0));
+        assert_eq!(Some((0, 9)), segmentation.drains_into(2, 9));
+        assert_eq!(None, segmentation.drains_into(0, 2));
+        assert_eq!(Some(3), segmentation.label(4, 9));
+
+        // When each basin has a single low point, flow and walls give the same basins.
+        let mut areas = segmentation.areas();
+        areas.sort_unstable_by(|a, b| b.cmp(a));
+        let basins = Basins::find(&heights_map);
+        let wall_areas = basins.all().iter().map(|b| b.area()).collect::<Vec<_>>();
+        assert_eq!(wall_areas, areas);
+
+        // Two low points between the same walls: the 3 flows to the first 1 (lowest neighbor).
+        // The flat area of 2s drains as a whole, and the 4 flows into it.
+        let heights_map = parse_input("1319\n9999\n2224").unwrap();
+        let segmentation = Segmentation::new(&heights_map);
+        let expected = vec![
+            vec![Some(0), Some(0), Some(1), None],
+            vec![None, None, None, None],
+            vec![Some(2), Some(2), Some(2), Some(2)],
+        ];
+        let labels = (0..3)
+            .map(|x| (0..4).map(|y| segmentation.label(x, y)).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        assert_eq!(expected, labels);
+        assert_eq!(Some((0, 2)), segmentation.drains_into(0, 2));
+        assert_eq!(Some((2, 0)), segmentation.drains_into(2, 3));
+
+        // A plateau between two low points is split between them, and its cells flow towards
+        // the closest outlet (the first one in case of a tie).
+        let heights_map = parse_input("12221").unwrap();
+        let segmentation = Segmentation::new(&heights_map);
+        let labels = (0..5).map(|y| segmentation.label(0, y)).collect::<Vec<_>>();
+        assert_eq!(vec![Some(0), Some(0), Some(0), Some(1), Some(1)], labels);
+        assert_eq!(Some((0, 4)), segmentation.drains_into(0, 3));
+        assert_eq!(Some((0, 1)), segmentation.flow(0, 2));
+        let watershed = Watershed::new(&heights_map);
+        assert_eq!(vec![vec![3, 2, 1, 1, 2]], watershed.accumulation);
+        assert_eq!("o<+xo\n", watershed.to_char_map());
+    }
+
+    #[test]
+    fn test_connectivity() {
+        let heights_map = test_map();
+        assert_eq!(vec![(1, 0), (0, 1)], heights_map.neighbor_points(0, 0));
+        let heights_map = test_map().with_connectivity(Connectivity::Eight);
+        assert_eq!(
+            vec![(0, 1), (1, 0), (1, 1)],
+            heights_map.neighbor_points(0, 0)
+        );
+        assert_eq!(8, heights_map.neighbor_points(2, 2).len());
+        let heights_map = test_map().with_connectivity(Connectivity::Hex);
+        let even_row = vec![(1, 1), (1, 2), (2, 1), (2, 3), (3, 1), (3, 2)];
+        assert_eq!(even_row, heights_map.neighbor_points(2, 2));
+        let odd_row = vec![(0, 2), (0, 3), (1, 1), (1, 3), (2, 2), (2, 3)];
+        assert_eq!(odd_row, heights_map.neighbor_points(1, 2));
+
+        // Diagonals connect basins through the corners of walls, and break some low points.
+        let heights_map = parse_input("1949\n9299\n9993").unwrap();
+        assert_eq!(2 + 3 + 5 + 4, part_1(&heights_map));
+        assert_eq!(1, part_2(&heights_map));
+        let heights_map = heights_map.with_connectivity(Connectivity::Eight);
+        assert_eq!(2 + 4, part_1(&heights_map));
+        assert_eq!(3, part_2(&heights_map));
+        assert_eq!(2, Segmentation::new(&heights_map).len());
+    }
+
+    #[test]
+    fn test_walls() {
+        // With walls from height 7, the test map is split into more, smaller basins.
+        let heights_map = test_map().with_wall_height(7);
+        let basins = Basins::find(&heights_map);
+        let areas = basins.all().iter().map(|b| b.area()).collect::<Vec<_>>();
+        assert_eq!(vec![9, 4, 3, 3], areas);
+        assert_eq!(15, part_1(&heights_map));
+
+        // Walls at height 0: the 0 is not a low point anymore, but its two neighbors become ones.
+        let heights_map = test_map().with_wall(|height| height == 0 || height >= 9);
+        assert_eq!(15 - 1 + 2 + 2, part_1(&heights_map));
+        let segmentation = Segmentation::new(&heights_map);
+        assert_eq!(Some((0, 8)), segmentation.drains_into(0, 7));
+        assert_eq!(Some((1, 9)), segmentation.drains_into(2, 9));
+        assert_eq!(None, segmentation.drains_into(0, 9));
+    }
+
+    #[test]
+    fn test_watershed() {
+        let heights_map = test_map();
+        let watershed = Watershed::new(&heights_map);
+        assert_eq!(Some((0, 0)), watershed.directions[1][0]);
+        assert_eq!(None, watershed.directions[0][1]);
+        // Each basin drains into its low point, which accumulates the whole basin.
+        assert_eq!(3, watershed.accumulation[0][1]);
+        assert_eq!(14, watershed.accumulation[2][2]);
+        assert_eq!(0, watershed.accumulation[0][2]);
+        // Basins are separated by walls only, so they all merge over walls, at height 9.
+        let expected = vec![
+            Saddle { basins: (0, 1), cell: (0, 4), spill_height: 9 },
+            Saddle { basins: (0, 2), cell: (0, 2), spill_height: 9 },
+            Saddle { basins: (1, 3), cell: (1, 7), spill_height: 9 },
+        ];
+        assert_eq!(expected, watershed.saddles);
+
+        let heights_map = parse_input("12321\n23432\n12391").unwrap();
+        let watershed = Watershed::new(&heights_map);
+        let expected = vec![
+            Saddle { basins: (0, 1), cell: (0, 2), spill_height: 3 },
+            Saddle { basins: (0, 2), cell: (1, 0), spill_height: 2 },
+            Saddle { basins: (1, 3), cell: (1, 4), spill_height: 2 },
+        ];
+        assert_eq!(expected, watershed.saddles);
+        assert_eq!(6, watershed.accumulation[0][0]);
+        assert_eq!("o<x+o\nx+++x\no++#o\n", watershed.to_char_map());
+        let heights_map = parse_input("9999\n9199\n9939").unwrap();
+        let heights_map = heights_map.with_connectivity(Connectivity::Eight);
+        assert_eq!(
+            "####\n#o##\n##\\#\n",
+            Watershed::new(&heights_map).to_char_map()
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let expected = "\
+2*###BBBB*
+3#AAA#B#BB
+#A*AAA#C#B
+AAAAA#CCC#
+#A###C*CCC
+";
+        // The smallest basin isn't one of the three largest, so its heights are shown.
+        let rendered = render(&test_map(), ColorMode::Plain);
+        assert_eq!(expected, rendered);
+
+        let heights_map = parse_input("109\n989").unwrap();
+        let rendered = render(&heights_map, ColorMode::Ansi);
+        let expected = "\
+\x1b[38;5;234;42m1\x1b[0m\x1b[1;31;42m0\x1b[0m\x1b[38;5;255m9\x1b[0m
+\x1b[38;5;255m9\x1b[0m\x1b[38;5;252;42m8\x1b[0m\x1b[38;5;255m9\x1b[0m
+";
+        assert_eq!(expected, rendered);
+        assert_eq!(Ok(ColorMode::Plain), "plain".parse::<ColorMode>());
+        assert!("rainbow".parse::<ColorMode>().is_err());
+    }
 }
@@ -0,0 +1,102 @@
+use crate::basins::Basins;
+use crate::HeightsMap;
+use std::io::IsTerminal;
+use std::str::FromStr;
+
+/// How to render a map in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// ANSI escape codes: heights as a grey gradient, low points in bold red, and the three
+    /// largest basins on a green, yellow and blue background.
+    Ansi,
+    /// Plain ASCII: `#` for walls, `*` for low points, `A`, `B` and `C` for the three largest
+    /// basins, and the height for the other cells.
+    Plain,
+}
+
+impl ColorMode {
+    /// ANSI colours if the standard output is a terminal supporting them, unless disabled by the
+    /// `NO_COLOR` environment variable.
+    pub fn detect() -> ColorMode {
+        let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let dumb_terminal = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+        if std::io::stdout().is_terminal() && !no_color && !dumb_terminal {
+            ColorMode::Ansi
+        } else {
+            ColorMode::Plain
+        }
+    }
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorMode::detect()),
+            "color" => Ok(ColorMode::Ansi),
+            "plain" => Ok(ColorMode::Plain),
+            _ => Err(format!("Unknown color mode {}", s)),
+        }
+    }
+}
+
+const TOP_BASIN_BACKGROUNDS: [&str; 3] = ["42", "43", "44"];
+const TOP_BASIN_LETTERS: [char; 3] = ['A', 'B', 'C'];
+
+pub fn render(heights_map: &HeightsMap, mode: ColorMode) -> String {
+    let rows = heights_map.x_max + 1;
+    let columns = heights_map.y_max + 1;
+    let mut top_basins = vec![vec![None; columns]; rows];
+    for (rank, basin) in Basins::find(heights_map).top_k(3).iter().enumerate() {
+        for (x, y) in &basin.cells {
+            top_basins[*x][*y] = Some(rank);
+        }
+    }
+    let max_height = heights_map
+        .heights
+        .iter()
+        .flatten()
+        .max()
+        .copied()
+        .unwrap_or(0);
+
+    let mut output = String::new();
+    for (x, row) in top_basins.iter().enumerate() {
+        for (y, top_basin) in row.iter().enumerate() {
+            let height = heights_map.height(x, y);
+            let is_low_point = heights_map.low_point_risk_level(x, y).is_some();
+            match mode {
+                ColorMode::Plain => output.push(if heights_map.is_wall(x, y) {
+                    '#'
+                } else if is_low_point {
+                    '*'
+                } else if let Some(rank) = top_basin {
+                    TOP_BASIN_LETTERS[*rank]
+                } else {
+                    height_char(height)
+                }),
+                ColorMode::Ansi => {
+                    let mut codes = Vec::new();
+                    if is_low_point {
+                        codes.push("1;31".to_owned());
+                    } else {
+                        // 24 shades of grey, from dark for the lowest cells to white.
+                        let shade = 232 + 23 * height / max_height.max(1);
+                        codes.push(format!("38;5;{}", shade));
+                    }
+                    if let Some(rank) = top_basin {
+                        codes.push(TOP_BASIN_BACKGROUNDS[*rank].to_owned());
+                    }
+                    output += &format!("\x1b[{}m{}\x1b[0m", codes.join(";"), height_char(height));
+                }
+            }
+        }
+        output.push('\n');
+    }
+    output
+}
+
+fn height_char(height: usize) -> char {
+    std::char::from_digit(height as u32, 10).unwrap_or('+')
+}
@@ -0,0 +1,125 @@
+use crate::union_find::UnionFind;
+use crate::HeightsMap;
+use std::collections::VecDeque;
+
+/// Assignment of each cell to the basin it drains into.
+///
+/// Each cell flows to its lowest neighbor, if that neighbor is strictly lower (the first one in
+/// neighbor order, in case of a tie). On a flat area with such outlets, each cell flows towards
+/// the closest outlet instead, so that a plateau between two low points is split between them. A
+/// flat area without any outlet is a sink, and drains as a whole. Walls don't flow and don't
+/// belong to any basin.
+pub struct Segmentation {
+    /// Basin of each cell, `None` for walls.
+    labels: Vec<Vec<Option<usize>>>,
+    /// Low point of each basin: its lowest cell (the first one in row order, in case of a tie).
+    low_points: Vec<(usize, usize)>,
+    /// Cell to which each cell flows, `None` for sinks and walls.
+    flow: Vec<Vec<Option<(usize, usize)>>>,
+}
+
+impl Segmentation {
+    pub fn new(heights_map: &HeightsMap) -> Segmentation {
+        let width = heights_map.y_max + 1;
+        let index = |(x, y): (usize, usize)| x * width + y;
+        let is_wall = |(x, y): (usize, usize)| heights_map.is_wall(x, y);
+        let cells = (heights_map.x_max + 1) * width;
+
+        // Cells with a lower neighbor flow to it. Then, breadth-first from these outlets, flat
+        // cells flow to the neighbor of the same height which is closer to an outlet.
+        let mut flow = vec![vec![None; width]; heights_map.x_max + 1];
+        let mut queue = VecDeque::new();
+        for (x, row) in flow.iter_mut().enumerate() {
+            for (y, target) in row.iter_mut().enumerate() {
+                *target = heights_map.steepest_descent(x, y);
+                if target.is_some() {
+                    queue.push_back((x, y));
+                }
+            }
+        }
+        while let Some((x, y)) = queue.pop_front() {
+            let height = heights_map.height(x, y);
+            for (n_x, n_y) in heights_map.neighbor_points(x, y) {
+                let is_flat = heights_map.height(n_x, n_y) == height && !is_wall((n_x, n_y));
+                if is_flat && flow[n_x][n_y].is_none() {
+                    flow[n_x][n_y] = Some((x, y));
+                    queue.push_back((n_x, n_y));
+                }
+            }
+        }
+
+        // Each cell joins the basin of its flow target, and the cells of a flat sink join each
+        // other.
+        let mut sets = UnionFind::new(cells);
+        for (x, row) in flow.iter().enumerate() {
+            for (y, target) in row.iter().enumerate() {
+                if is_wall((x, y)) {
+                    continue;
+                }
+                match *target {
+                    Some(target) => sets.union(index((x, y)), index(target)),
+                    None => {
+                        let height = heights_map.height(x, y);
+                        for (n_x, n_y) in heights_map.neighbor_points(x, y) {
+                            if heights_map.height(n_x, n_y) == height && !is_wall((n_x, n_y)) {
+                                sets.union(index((x, y)), index((n_x, n_y)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // Number the basins in the row order of their first cell.
+        let mut labels = vec![vec![None; width]; heights_map.x_max + 1];
+        let mut label_of_root = vec![None; cells];
+        let mut low_points: Vec<(usize, usize)> = Vec::new();
+        for (x, row) in labels.iter_mut().enumerate() {
+            for (y, cell_label) in row.iter_mut().enumerate() {
+                if is_wall((x, y)) {
+                    continue;
+                }
+                let root = sets.find(index((x, y)));
+                let label = *label_of_root[root].get_or_insert_with(|| {
+                    low_points.push((x, y));
+                    low_points.len() - 1
+                });
+                let (low_x, low_y) = low_points[label];
+                if heights_map.height(x, y) < heights_map.height(low_x, low_y) {
+                    low_points[label] = (x, y);
+                }
+                *cell_label = Some(label);
+            }
+        }
+        Segmentation { labels, low_points, flow }
+    }
+
+    /// Cell to which a cell flows, or `None` for a sink or a wall.
+    pub fn flow(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        self.flow[x][y]
+    }
+
+    /// Number of basins.
+    pub fn len(&self) -> usize {
+        self.low_points.len()
+    }
+
+    /// Basin of a cell, or `None` for a wall.
+    pub fn label(&self, x: usize, y: usize) -> Option<usize> {
+        self.labels[x][y]
+    }
+
+    /// Low point of the basin a cell drains into, or `None` for a wall.
+    pub fn drains_into(&self, x: usize, y: usize) -> Option<(usize, usize)> {
+        self.label(x, y).map(|label| self.low_points[label])
+    }
+
+    /// Number of cells of each basin.
+    pub fn areas(&self) -> Vec<usize> {
+        let mut areas = vec![0; self.len()];
+        for label in self.labels.iter().flatten().flatten() {
+            areas[*label] += 1;
+        }
+        areas
+    }
+}
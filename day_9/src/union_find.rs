@@ -0,0 +1,42 @@
+/// Disjoint sets of `0..size`, with union by rank and path compression.
+pub struct UnionFind {
+    parents: Vec<usize>,
+    ranks: Vec<u8>,
+}
+
+impl UnionFind {
+    pub fn new(size: usize) -> UnionFind {
+        UnionFind { parents: (0..size).collect(), ranks: vec![0; size] }
+    }
+
+    /// Representative of the set containing `element`.
+    pub fn find(&mut self, element: usize) -> usize {
+        let mut root = element;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+        let mut current = element;
+        while self.parents[current] != root {
+            let next = self.parents[current];
+            self.parents[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    /// Merges the sets containing `a` and `b`.
+    pub fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        match self.ranks[a].cmp(&self.ranks[b]) {
+            std::cmp::Ordering::Less => self.parents[a] = b,
+            std::cmp::Ordering::Greater => self.parents[b] = a,
+            std::cmp::Ordering::Equal => {
+                self.parents[b] = a;
+                self.ranks[a] += 1;
+            }
+        }
+    }
+}
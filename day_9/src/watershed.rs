@@ -0,0 +1,155 @@
+use crate::segmentation::Segmentation;
+use crate::union_find::UnionFind;
+use crate::HeightsMap;
+use std::cmp::Ordering;
+
+/// Lowest pass between two basins, possibly over walls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Saddle {
+    /// Labels of the two basins (as in [`Segmentation`]), the smallest one first.
+    pub basins: (usize, usize),
+    /// Highest cell on the lowest path between the two basins, through which water spills from
+    /// one basin into the other.
+    pub cell: (usize, usize),
+    /// Water level at which the two basins merge.
+    pub spill_height: usize,
+}
+
+/// Hydrology of a height map: where the water flows, how much flows through each cell, and where
+/// basins meet.
+pub struct Watershed {
+    pub segmentation: Segmentation,
+    /// Cell to which each cell flows (see [`Segmentation`]), `None` for sinks and walls.
+    pub directions: Vec<Vec<Option<(usize, usize)>>>,
+    /// Number of cells whose water flows through each cell, including itself (0 for walls).
+    pub accumulation: Vec<Vec<usize>>,
+    /// Whether each cell is next to a cell of another basin.
+    pub ridges: Vec<Vec<bool>>,
+    /// Passes through which the basins merge as the water level rises, ordered by basins. Each
+    /// time two flooded areas meet, their deepest basins merge, so that the saddles form a tree
+    /// over all the basins.
+    pub saddles: Vec<Saddle>,
+}
+
+impl Watershed {
+    pub fn new(heights_map: &HeightsMap) -> Watershed {
+        let segmentation = Segmentation::new(heights_map);
+        let (rows, columns) = (heights_map.x_max + 1, heights_map.y_max + 1);
+        let cells = (0..rows).flat_map(|x| (0..columns).map(move |y| (x, y)));
+
+        let mut directions = vec![vec![None; columns]; rows];
+        for (x, y) in cells.clone() {
+            directions[x][y] = segmentation.flow(x, y);
+        }
+
+        // A cell pushes its water downstream once all the water from upstream has reached it
+        // (the flow never loops, even on flat areas).
+        let mut accumulation = vec![vec![0; columns]; rows];
+        let mut upstream = vec![vec![0; columns]; rows];
+        for (x, y) in cells.clone() {
+            if let Some((down_x, down_y)) = directions[x][y] {
+                upstream[down_x][down_y] += 1;
+            }
+        }
+        let mut ready = cells
+            .clone()
+            .filter(|(x, y)| !heights_map.is_wall(*x, *y) && upstream[*x][*y] == 0)
+            .collect::<Vec<_>>();
+        while let Some((x, y)) = ready.pop() {
+            accumulation[x][y] += 1;
+            if let Some((down_x, down_y)) = directions[x][y] {
+                accumulation[down_x][down_y] += accumulation[x][y];
+                upstream[down_x][down_y] -= 1;
+                if upstream[down_x][down_y] == 0 {
+                    ready.push((down_x, down_y));
+                }
+            }
+        }
+
+        let mut ridges = vec![vec![false; columns]; rows];
+        for (x, y) in cells.clone() {
+            if let Some(label) = segmentation.label(x, y) {
+                ridges[x][y] = heights_map
+                    .neighbor_points(x, y)
+                    .into_iter()
+                    .any(|(n_x, n_y)| segmentation.label(n_x, n_y).is_some_and(|l| l != label));
+            }
+        }
+
+        // Flood the map from the lowest cells up, walls included (low points come first, as they
+        // are the first of their basin's lowest cells in row order). Each flooded area keeps
+        // track of its deepest basin (lowest low point, then smallest label), and when two areas
+        // with basins meet, their deepest basins merge at the height of the cell which joins them.
+        let index = |(x, y): (usize, usize)| x * columns + y;
+        let mut sorted_cells = cells.collect::<Vec<_>>();
+        sorted_cells.sort_by_key(|(x, y)| heights_map.height(*x, *y));
+        let mut flooded = vec![false; rows * columns];
+        let mut deepest_basins: Vec<Option<(usize, usize)>> = vec![None; rows * columns];
+        let mut areas = UnionFind::new(rows * columns);
+        let mut saddles = Vec::new();
+        for (x, y) in sorted_cells {
+            let spill_height = heights_map.height(x, y);
+            flooded[index((x, y))] = true;
+            if segmentation.drains_into(x, y) == Some((x, y)) {
+                deepest_basins[index((x, y))] =
+                    segmentation.label(x, y).map(|label| (spill_height, label));
+            }
+            for neighbor in heights_map.neighbor_points(x, y) {
+                if !flooded[index(neighbor)] {
+                    continue;
+                }
+                let (root, other_root) = (areas.find(index((x, y))), areas.find(index(neighbor)));
+                if root == other_root {
+                    continue;
+                }
+                let (deepest, other_deepest) = (deepest_basins[root], deepest_basins[other_root]);
+                match (deepest, other_deepest) {
+                    (Some((_, label)), Some((_, other))) if label != other => {
+                        let basins = (label.min(other), label.max(other));
+                        saddles.push(Saddle { basins, cell: (x, y), spill_height });
+                    }
+                    _ => {}
+                }
+                areas.union(root, other_root);
+                deepest_basins[areas.find(root)] = match (deepest, other_deepest) {
+                    (Some(deepest), Some(other_deepest)) => Some(deepest.min(other_deepest)),
+                    _ => deepest.or(other_deepest),
+                };
+            }
+        }
+        saddles.sort_by_key(|saddle| saddle.basins);
+
+        Watershed { segmentation, directions, accumulation, ridges, saddles }
+    }
+
+    /// One character per cell: `o` for sinks, `x` for saddles, `#` for other walls, `+` for other
+    /// ridge cells, and otherwise an arrow towards the flow direction (`^`, `v`, `<`, `>`, or `\`
+    /// and `/` for diagonals). Sinks take precedence over saddles and ridges.
+    pub fn to_char_map(&self) -> String {
+        let mut map = String::new();
+        for (x, row) in self.directions.iter().enumerate() {
+            for (y, direction) in row.iter().enumerate() {
+                let is_saddle = self.saddles.iter().any(|saddle| saddle.cell == (x, y));
+                let is_wall = self.segmentation.label(x, y).is_none();
+                let c = match direction {
+                    None if !is_wall => 'o',
+                    _ if is_saddle => 'x',
+                    None => '#',
+                    _ if self.ridges[x][y] => '+',
+                    Some((d_x, d_y)) => match (d_x.cmp(&x), d_y.cmp(&y)) {
+                        (Ordering::Less, Ordering::Equal) => '^',
+                        (Ordering::Greater, Ordering::Equal) => 'v',
+                        (Ordering::Equal, Ordering::Less) => '<',
+                        (Ordering::Equal, Ordering::Greater) => '>',
+                        (Ordering::Less, Ordering::Less)
+                        | (Ordering::Greater, Ordering::Greater) => '\\',
+                        _ => '/',
+                    },
+                };
+                map.push(c);
+            }
+            map.push('\n');
+        }
+        map
+    }
+}